@@ -0,0 +1,175 @@
+// Copyright 2018 Martin Billinger
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Parsing benchmarks over generated data sets that resemble typical ARFF files.
+//!
+//! Run with `cargo bench`.
+
+extern crate arff;
+#[macro_use]
+extern crate criterion;
+
+use std::fmt::Write;
+
+use arff::dynamic::{BadRows, DataSet};
+use criterion::{Criterion, Throughput};
+
+const N_ROWS: usize = 10_000;
+
+/// deterministic pseudo-random numbers, so that all runs parse the same input
+struct Lcg(u64);
+
+impl Lcg {
+    fn next(&mut self) -> u64 {
+        self.0 = self
+            .0
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        self.0 >> 33
+    }
+
+    fn float(&mut self) -> f64 {
+        self.next() as f64 / (1u64 << 31) as f64
+    }
+}
+
+/// measurements with a few decimals, like the UCI data sets
+fn floats() -> String {
+    let mut rng = Lcg(1);
+    let mut text = String::from("@RELATION floats\n");
+    for i in 0..10 {
+        writeln!(text, "@ATTRIBUTE x{} NUMERIC", i).unwrap();
+    }
+    text += "@DATA\n";
+    for _ in 0..N_ROWS {
+        for i in 0..10 {
+            let sep = if i == 9 { "\n" } else { "," };
+            match i % 3 {
+                0 => write!(text, "{:.2}{}", rng.float() * 10.0, sep),
+                1 => write!(text, "{:.6}{}", rng.float() * 2.0 - 1.0, sep),
+                _ => write!(text, "{:e}{}", rng.float() * 1e-3, sep),
+            }
+            .unwrap();
+        }
+    }
+    text
+}
+
+/// counts and identifiers
+fn integers() -> String {
+    let mut rng = Lcg(2);
+    let mut text = String::from("@RELATION integers\n");
+    for i in 0..10 {
+        writeln!(text, "@ATTRIBUTE n{} NUMERIC", i).unwrap();
+    }
+    text += "@DATA\n";
+    for row in 0..N_ROWS {
+        write!(text, "{}", row).unwrap();
+        for i in 1..10 {
+            let value = rng.next() as i64 % (10i64.pow(i as u32 % 6 + 1)) - 5;
+            write!(text, ",{}", value).unwrap();
+        }
+        text += "\n";
+    }
+    text
+}
+
+/// numeric, nominal and string attributes with a few missing values
+fn mixed() -> String {
+    let mut rng = Lcg(3);
+    let mut text = String::from(
+        "@RELATION mixed\n\
+         @ATTRIBUTE age NUMERIC\n\
+         @ATTRIBUTE weight NUMERIC\n\
+         @ATTRIBUTE name STRING\n\
+         @ATTRIBUTE smoker {yes, no}\n\
+         @ATTRIBUTE class {low, medium, high}\n\
+         @DATA\n",
+    );
+    for row in 0..N_ROWS {
+        let weight = if row % 17 == 0 {
+            "?".to_owned()
+        } else {
+            format!("{:.1}", 50.0 + rng.float() * 50.0)
+        };
+        writeln!(
+            text,
+            "{},{},'patient {}',{},{}",
+            rng.next() % 90,
+            weight,
+            row,
+            ["yes", "no"][rng.next() as usize % 2],
+            ["low", "medium", "high"][rng.next() as usize % 3]
+        )
+        .unwrap();
+    }
+    text
+}
+
+/// a nominal attribute with many categories, which must not be copied for every row
+fn wide_nominal() -> String {
+    let mut rng = Lcg(4);
+    let categories: Vec<_> = (0..2_000).map(|i| format!("c{}", i)).collect();
+    let mut text = String::from("@RELATION wide\n@ATTRIBUTE x NUMERIC\n");
+    writeln!(text, "@ATTRIBUTE class {{{}}}", categories.join(",")).unwrap();
+    text += "@DATA\n";
+    for row in 0..N_ROWS {
+        let class = &categories[rng.next() as usize % categories.len()];
+        writeln!(text, "{},{}", row, class).unwrap();
+    }
+    text
+}
+
+fn bench_dynamic(c: &mut Criterion) {
+    let mut group = c.benchmark_group("dynamic");
+    for &(name, ref input) in &[
+        ("floats", floats()),
+        ("integers", integers()),
+        ("mixed", mixed()),
+    ] {
+        group.throughput(Throughput::Bytes(input.len() as u64));
+        group.bench_function(name, |b| b.iter(|| DataSet::from_str(input).unwrap()));
+    }
+    group.finish();
+}
+
+/// lenient parsing of well-formed input should take about as long as `from_str`
+fn bench_lenient(c: &mut Criterion) {
+    let mut group = c.benchmark_group("lenient");
+    for &(name, ref input) in &[("mixed", mixed()), ("wide_nominal", wide_nominal())] {
+        group.throughput(Throughput::Bytes(input.len() as u64));
+        group.bench_function(format!("{}/strict", name), |b| {
+            b.iter(|| DataSet::from_str(input).unwrap())
+        });
+        group.bench_function(format!("{}/lenient", name), |b| {
+            b.iter(|| DataSet::from_str_lenient(input, BadRows::Skip).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn bench_serde(c: &mut Criterion) {
+    let mut group = c.benchmark_group("serde");
+
+    let input = floats();
+    group.throughput(Throughput::Bytes(input.len() as u64));
+    group.bench_function("floats", |b| {
+        b.iter(|| arff::from_str::<Vec<[f64; 10]>>(&input).unwrap())
+    });
+
+    let input = integers();
+    group.throughput(Throughput::Bytes(input.len() as u64));
+    group.bench_function("integers", |b| {
+        b.iter(|| arff::from_str::<Vec<[i64; 10]>>(&input).unwrap())
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_dynamic, bench_lenient, bench_serde);
+criterion_main!(benches);
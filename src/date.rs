@@ -0,0 +1,600 @@
+// Copyright 2018 Martin Billinger
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Support for the ARFF `DATE` attribute type.
+//!
+//! Weka describes date formats with Java's `SimpleDateFormat` patterns. This module implements
+//! the subset of pattern letters that is commonly found in ARFF files:
+//!
+//! | Letter | Meaning                   | Example     |
+//! |--------|---------------------------|-------------|
+//! | `y`    | year                      | `2018`, `18`|
+//! | `M`    | month                     | `07`, `Jul`, `July` |
+//! | `d`    | day of month              | `10`        |
+//! | `H`    | hour (0-23)               | `0`         |
+//! | `h`    | hour (1-12)               | `12`        |
+//! | `a`    | AM/PM marker              | `PM`        |
+//! | `m`    | minute                    | `30`        |
+//! | `s`    | second                    | `55`        |
+//! | `S`    | millisecond               | `978`       |
+//! | `Z`    | time zone offset          | `+0100`     |
+//!
+//! Text enclosed in single quotes is copied verbatim, and `''` represents a single quote. Dates
+//! are always formatted in UTC.
+
+use std::fmt;
+
+use serde::de::{self, Deserialize, Deserializer, Visitor};
+use serde::ser::{Serialize, Serializer};
+
+use super::error::{Error, Result};
+
+/// The date format Weka uses if an attribute does not specify one.
+pub const DEFAULT_FORMAT: &str = "yyyy-MM-dd'T'HH:mm:ss";
+
+/// Name of the newtype struct that identifies a `Date` to the ARFF (de)serializers.
+pub(crate) const DATE_TOKEN: &str = "$arff::private::Date";
+
+const MILLIS_PER_SECOND: i64 = 1000;
+const MILLIS_PER_MINUTE: i64 = 60 * MILLIS_PER_SECOND;
+const MILLIS_PER_HOUR: i64 = 60 * MILLIS_PER_MINUTE;
+const MILLIS_PER_DAY: i64 = 24 * MILLIS_PER_HOUR;
+
+/// Milliseconds in an `i64` cover about 292 million years around the epoch. Larger years are
+/// rejected before computing with them.
+const MAX_YEAR: i64 = 300_000_000;
+
+const MONTH_NAMES: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+/// A point in time, stored as milliseconds since the Unix epoch (UTC).
+///
+/// Fields of this type are serialized as ARFF `DATE` attributes. When deserializing, the value
+/// is parsed according to the format declared in the ARFF header.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Date {
+    millis: i64,
+}
+
+impl Date {
+    /// Create a date from milliseconds since the Unix epoch.
+    pub fn from_millis(millis: i64) -> Self {
+        Date { millis }
+    }
+
+    /// Create a date from calendar date and time of day (UTC).
+    ///
+    /// Returns `None` if any of the components is out of range.
+    pub fn from_ymd_hms(
+        year: i64,
+        month: u32,
+        day: u32,
+        hour: u32,
+        minute: u32,
+        second: u32,
+    ) -> Option<Self> {
+        if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) {
+            return None;
+        }
+        if hour > 23 || minute > 59 || second > 59 {
+            return None;
+        }
+        let millis = checked_millis(year, month, day, hour as i64, minute as i64, second as i64)?;
+        Some(Date { millis })
+    }
+
+    /// Milliseconds since the Unix epoch.
+    pub fn millis(&self) -> i64 {
+        self.millis
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&DateFormat::default().format(self.millis))
+    }
+}
+
+impl Serialize for Date {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_newtype_struct(DATE_TOKEN, &self.millis)
+    }
+}
+
+impl<'de> Deserialize<'de> for Date {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct DateVisitor;
+
+        impl<'de> Visitor<'de> for DateVisitor {
+            type Value = Date;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a date")
+            }
+
+            fn visit_newtype_struct<D>(self, deserializer: D) -> std::result::Result<Date, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                i64::deserialize(deserializer).map(Date::from_millis)
+            }
+
+            fn visit_i64<E>(self, v: i64) -> std::result::Result<Date, E>
+            where
+                E: de::Error,
+            {
+                Ok(Date::from_millis(v))
+            }
+        }
+
+        deserializer.deserialize_newtype_struct(DATE_TOKEN, DateVisitor)
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum Token {
+    Year(usize),
+    Month(usize),
+    Day(usize),
+    Hour(usize),
+    Hour12(usize),
+    AmPm,
+    Minute(usize),
+    Second(usize),
+    Millis(usize),
+    Zone,
+    Literal(String),
+}
+
+impl Token {
+    fn is_numeric(&self) -> bool {
+        match *self {
+            Token::Month(n) => n < 3,
+            Token::Year(_)
+            | Token::Day(_)
+            | Token::Hour(_)
+            | Token::Hour12(_)
+            | Token::Minute(_)
+            | Token::Second(_)
+            | Token::Millis(_) => true,
+            Token::AmPm | Token::Zone | Token::Literal(_) => false,
+        }
+    }
+}
+
+/// A compiled `SimpleDateFormat` pattern
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct DateFormat {
+    pattern: String,
+    tokens: Vec<Token>,
+}
+
+impl Default for DateFormat {
+    fn default() -> Self {
+        DateFormat::new(DEFAULT_FORMAT).unwrap()
+    }
+}
+
+impl DateFormat {
+    /// Compile a date format pattern.
+    pub fn new(pattern: &str) -> Result<Self> {
+        let invalid = || Error::InvalidDateFormat(pattern.to_owned());
+
+        let mut tokens = Vec::new();
+        let mut chars = pattern.chars().peekable();
+
+        while let Some(ch) = chars.next() {
+            if ch == '\'' {
+                let mut literal = String::new();
+                if chars.peek() == Some(&'\'') {
+                    chars.next();
+                    literal.push('\'');
+                } else {
+                    loop {
+                        match chars.next() {
+                            None => return Err(invalid()),
+                            Some('\'') => {
+                                if chars.peek() == Some(&'\'') {
+                                    chars.next();
+                                    literal.push('\'');
+                                } else {
+                                    break;
+                                }
+                            }
+                            Some(c) => literal.push(c),
+                        }
+                    }
+                }
+                push_literal(&mut tokens, &literal);
+                continue;
+            }
+
+            if !ch.is_ascii_alphabetic() {
+                push_literal(&mut tokens, &ch.to_string());
+                continue;
+            }
+
+            let mut count = 1;
+            while chars.peek() == Some(&ch) {
+                chars.next();
+                count += 1;
+            }
+
+            tokens.push(match ch {
+                'y' => Token::Year(count),
+                'M' => Token::Month(count),
+                'd' => Token::Day(count),
+                'H' => Token::Hour(count),
+                'h' => Token::Hour12(count),
+                'a' => Token::AmPm,
+                'm' => Token::Minute(count),
+                's' => Token::Second(count),
+                'S' => Token::Millis(count),
+                'Z' => Token::Zone,
+                _ => return Err(invalid()),
+            });
+        }
+
+        Ok(DateFormat {
+            pattern: pattern.to_owned(),
+            tokens,
+        })
+    }
+
+    /// The pattern this format was compiled from
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Format milliseconds since the Unix epoch as a date string (UTC).
+    pub fn format(&self, millis: i64) -> String {
+        let days = div_floor(millis, MILLIS_PER_DAY);
+        let time = millis - days * MILLIS_PER_DAY;
+        let (year, month, day) = civil_from_days(days);
+        let hour = time / MILLIS_PER_HOUR;
+        let minute = time % MILLIS_PER_HOUR / MILLIS_PER_MINUTE;
+        let second = time % MILLIS_PER_MINUTE / MILLIS_PER_SECOND;
+        let milli = time % MILLIS_PER_SECOND;
+
+        let mut s = String::new();
+        for token in &self.tokens {
+            match *token {
+                Token::Year(2) => push_number(&mut s, year.rem_euclid(100), 2),
+                Token::Year(n) => push_number(&mut s, year, n),
+                Token::Month(n) if n >= 4 => s += MONTH_NAMES[month as usize - 1],
+                Token::Month(3) => s += &MONTH_NAMES[month as usize - 1][..3],
+                Token::Month(n) => push_number(&mut s, month as i64, n),
+                Token::Day(n) => push_number(&mut s, day as i64, n),
+                Token::Hour(n) => push_number(&mut s, hour, n),
+                Token::Hour12(n) => push_number(&mut s, (hour + 11) % 12 + 1, n),
+                Token::AmPm => s += if hour < 12 { "AM" } else { "PM" },
+                Token::Minute(n) => push_number(&mut s, minute, n),
+                Token::Second(n) => push_number(&mut s, second, n),
+                Token::Millis(n) => push_number(&mut s, milli, n),
+                Token::Zone => s += "+0000",
+                Token::Literal(ref lit) => s += lit,
+            }
+        }
+        s
+    }
+
+    /// Parse a date string into milliseconds since the Unix epoch.
+    ///
+    /// Returns `None` if the string does not match the format.
+    pub fn parse(&self, input: &str) -> Option<i64> {
+        let mut input = input.as_bytes();
+
+        let mut year = 1970;
+        let mut month = 1;
+        let mut day = 1;
+        let mut hour = 0;
+        let mut pm = None;
+        let mut minute = 0;
+        let mut second = 0;
+        let mut milli = 0;
+        let mut offset = 0;
+
+        for (i, token) in self.tokens.iter().enumerate() {
+            // numeric fields that are directly followed by another numeric field have fixed width
+            let width = match self.tokens.get(i + 1) {
+                Some(next) if next.is_numeric() => match *token {
+                    Token::Year(n)
+                    | Token::Month(n)
+                    | Token::Day(n)
+                    | Token::Hour(n)
+                    | Token::Hour12(n)
+                    | Token::Minute(n)
+                    | Token::Second(n)
+                    | Token::Millis(n) => n,
+                    _ => 0,
+                },
+                _ => 0,
+            };
+
+            match *token {
+                Token::Year(n) => {
+                    let (value, digits) = parse_number(&mut input, width)?;
+                    year = if n <= 2 && digits == 2 {
+                        if value < 50 {
+                            2000 + value
+                        } else {
+                            1900 + value
+                        }
+                    } else {
+                        value
+                    };
+                }
+                Token::Month(n) if n >= 3 => {
+                    let idx = MONTH_NAMES.iter().position(|name| {
+                        let name = if n == 3 { &name[..3] } else { name };
+                        starts_with_ignore_case(input, name)
+                    })?;
+                    input = &input[if n == 3 { 3 } else { MONTH_NAMES[idx].len() }..];
+                    month = idx as i64 + 1;
+                }
+                Token::Month(_) => month = parse_number(&mut input, width)?.0,
+                Token::Day(_) => day = parse_number(&mut input, width)?.0,
+                Token::Hour(_) => hour = parse_number(&mut input, width)?.0,
+                Token::Hour12(_) => {
+                    hour = parse_number(&mut input, width)?.0;
+                    if !(1..=12).contains(&hour) {
+                        return None;
+                    }
+                    hour %= 12;
+                }
+                Token::AmPm => {
+                    if starts_with_ignore_case(input, "AM") {
+                        pm = Some(false);
+                    } else if starts_with_ignore_case(input, "PM") {
+                        pm = Some(true);
+                    } else {
+                        return None;
+                    }
+                    input = &input[2..];
+                }
+                Token::Minute(_) => minute = parse_number(&mut input, width)?.0,
+                Token::Second(_) => second = parse_number(&mut input, width)?.0,
+                Token::Millis(_) => milli = parse_number(&mut input, width)?.0,
+                Token::Zone => offset = parse_zone(&mut input)?,
+                Token::Literal(ref lit) => {
+                    if !input.starts_with(lit.as_bytes()) {
+                        return None;
+                    }
+                    input = &input[lit.len()..];
+                }
+            }
+        }
+
+        if !input.is_empty() {
+            return None;
+        }
+
+        if pm == Some(true) {
+            hour += 12;
+        }
+
+        if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month as u32) as i64 {
+            return None;
+        }
+        if hour > 23 || minute > 59 || second > 59 || milli > 999 {
+            return None;
+        }
+
+        checked_millis(year, month as u32, day as u32, hour, minute, second)?
+            .checked_add(milli)?
+            .checked_sub(offset)
+    }
+}
+
+fn push_literal(tokens: &mut Vec<Token>, text: &str) {
+    if let Some(&mut Token::Literal(ref mut lit)) = tokens.last_mut() {
+        lit.push_str(text);
+        return;
+    }
+    tokens.push(Token::Literal(text.to_owned()))
+}
+
+fn push_number(s: &mut String, value: i64, width: usize) {
+    s.push_str(&format!("{:01$}", value, width));
+}
+
+/// Parse an unsigned decimal number. If `width` is non-zero exactly that many digits are
+/// consumed. Returns the value and the number of digits read.
+fn parse_number(input: &mut &[u8], width: usize) -> Option<(i64, usize)> {
+    let mut value: i64 = 0;
+    let mut digits = 0;
+    while digits < input.len() && (width == 0 || digits < width) {
+        match input[digits] {
+            ch @ b'0'..=b'9' => {
+                value = value.checked_mul(10)?.checked_add((ch - b'0') as i64)?;
+                digits += 1;
+            }
+            _ => break,
+        }
+    }
+    if digits == 0 || (width > 0 && digits < width) {
+        return None;
+    }
+    *input = &input[digits..];
+    Some((value, digits))
+}
+
+/// Parse a time zone offset of the form `+hhmm`, `-hhmm`, or `Z` into milliseconds.
+fn parse_zone(input: &mut &[u8]) -> Option<i64> {
+    let sign = match input.first() {
+        Some(&b'Z') => {
+            *input = &input[1..];
+            return Some(0);
+        }
+        Some(&b'+') => 1,
+        Some(&b'-') => -1,
+        _ => return None,
+    };
+    *input = &input[1..];
+    let (hours, _) = parse_number(input, 2)?;
+    if input.first() == Some(&b':') {
+        *input = &input[1..];
+    }
+    let (minutes, _) = parse_number(input, 2)?;
+    Some(sign * (hours * MILLIS_PER_HOUR + minutes * MILLIS_PER_MINUTE))
+}
+
+fn starts_with_ignore_case(input: &[u8], prefix: &str) -> bool {
+    input.len() >= prefix.len() && input[..prefix.len()].eq_ignore_ascii_case(prefix.as_bytes())
+}
+
+fn div_floor(a: i64, b: i64) -> i64 {
+    let d = a / b;
+    if a % b < 0 {
+        d - 1
+    } else {
+        d
+    }
+}
+
+fn is_leap_year(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Milliseconds since the Unix epoch of a valid date and time, or `None` if they do not fit
+/// into an `i64`
+fn checked_millis(
+    year: i64,
+    month: u32,
+    day: u32,
+    hour: i64,
+    minute: i64,
+    second: i64,
+) -> Option<i64> {
+    if !(-MAX_YEAR..=MAX_YEAR).contains(&year) {
+        return None;
+    }
+    days_from_civil(year, month, day)
+        .checked_mul(MILLIS_PER_DAY)?
+        .checked_add(
+            hour * MILLIS_PER_HOUR + minute * MILLIS_PER_MINUTE + second * MILLIS_PER_SECOND,
+        )
+}
+
+/// Number of days since 1970-01-01 of the given proleptic Gregorian date
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = div_floor(y, 400);
+    let yoe = y - era * 400;
+    let mp = (month as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Proleptic Gregorian date of the given number of days since 1970-01-01
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = div_floor(z, 146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_format() {
+        let fmt = DateFormat::default();
+        assert_eq!(fmt.parse("1970-01-01T00:00:00"), Some(0));
+        assert_eq!(fmt.parse("2001-04-03T12:12:12"), Some(986_299_932_000));
+        assert_eq!(fmt.format(986_299_932_000), "2001-04-03T12:12:12");
+        assert_eq!(fmt.format(-1000), "1969-12-31T23:59:59");
+        assert_eq!(fmt.parse("2001-04-03"), None);
+        assert_eq!(fmt.parse("2001-02-29T00:00:00"), None);
+    }
+
+    #[test]
+    fn custom_formats() {
+        let fmt = DateFormat::new("yyyy-MM-dd HH:mm:ss").unwrap();
+        assert_eq!(fmt.parse("2018-07-10 23:01:02"), Some(1_531_263_662_000));
+        assert_eq!(fmt.format(1_531_263_662_000), "2018-07-10 23:01:02");
+
+        let fmt = DateFormat::new("dd-MMM-yy hh:mm a").unwrap();
+        assert_eq!(fmt.format(1_531_263_662_000), "10-Jul-18 11:01 PM");
+        assert_eq!(fmt.parse("10-jul-18 11:01 PM"), Some(1_531_263_660_000));
+
+        let fmt = DateFormat::new("yyyyMMdd'T'HHmmss.SSSZ").unwrap();
+        assert_eq!(
+            fmt.parse("20180710T230102.500+0100"),
+            Some(1_531_260_062_500)
+        );
+        assert_eq!(fmt.format(1_531_260_062_500), "20180710T220102.500+0000");
+
+        let fmt = DateFormat::new("'o''clock' H").unwrap();
+        assert_eq!(fmt.format(3 * MILLIS_PER_HOUR), "o'clock 3");
+    }
+
+    #[test]
+    fn invalid_formats() {
+        assert!(DateFormat::new("yyyy-MM-dd EEE").is_err());
+        assert!(DateFormat::new("yyyy 'unterminated").is_err());
+    }
+
+    #[test]
+    fn date_components() {
+        let date = Date::from_ymd_hms(2001, 4, 3, 12, 12, 12).unwrap();
+        assert_eq!(date.millis(), 986_299_932_000);
+        assert_eq!(date.to_string(), "2001-04-03T12:12:12");
+        assert_eq!(Date::from_ymd_hms(2001, 13, 3, 12, 12, 12), None);
+        assert_eq!(Date::from_ymd_hms(i64::MAX, 1, 1, 0, 0, 0), None);
+    }
+
+    #[test]
+    fn out_of_range() {
+        let fmt = DateFormat::new("yyyy-MM-dd").unwrap();
+        assert_eq!(fmt.parse("300000000000000-01-01"), None);
+        assert_eq!(fmt.parse("292278994-12-31"), None);
+        assert_eq!(
+            fmt.parse("200000000-01-01"),
+            Some(6_311_328_232_780_800_000)
+        );
+
+        let fmt = DateFormat::new("yyyy-MM-ddZ").unwrap();
+        assert_eq!(fmt.parse("292278994-08-17-1200"), None);
+    }
+}
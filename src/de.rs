@@ -8,12 +8,19 @@
 
 //! Deserialize ARFF formatted text to a Rust data structure.
 
+use std::borrow::Cow;
+use std::io::BufRead;
+use std::marker::PhantomData;
+
 use serde::de::{
-    self, Deserialize, DeserializeSeed, IntoDeserializer, MapAccess, SeqAccess, Visitor,
+    self, Deserialize, DeserializeOwned, DeserializeSeed, IntoDeserializer, MapAccess, SeqAccess,
+    Visitor,
 };
 
-use super::error::{Error, Result};
+use super::date::{DateFormat, DATE_TOKEN};
+use super::error::{Diagnostic, Error, Result};
 use super::parser::*;
+use super::weighted::{DEFAULT_WEIGHT, WEIGHTED_TOKEN};
 
 /// Deserialize an instance of type `T` from an ARFF formatted string.
 pub fn from_str<'a, T>(s: &'a str) -> Result<T>
@@ -29,6 +36,101 @@ where
     Ok(t)
 }
 
+/// Deserialize an instance of type `T` from an ARFF formatted string, checking the values
+/// against the declared attribute types.
+///
+/// In particular, values of `INTEGER` attributes must be whole numbers, even if they are
+/// deserialized into floating point types.
+pub fn from_str_strict<'a, T>(s: &'a str) -> Result<T>
+where
+    T: Deserialize<'a>,
+{
+    let mut deserializer = Deserializer::from_str(s)?;
+    deserializer.set_strict(true);
+
+    let t = T::deserialize(&mut deserializer)?;
+
+    deserializer.parser.parse_eof()?;
+
+    Ok(t)
+}
+
+/// Read the header of an ARFF formatted string, without parsing the data rows.
+///
+/// Parsing stops after the `@DATA` declaration.
+pub fn read_header(input: &str) -> Result<Header> {
+    Parser::new(input).parse_header()
+}
+
+/// Deserialize an instance of type `T` from a reader of ARFF formatted text.
+///
+/// The input is parsed incrementally, so it never needs to be held in memory as a whole.
+pub fn from_reader<R, T>(reader: R) -> Result<T>
+where
+    R: BufRead,
+    T: DeserializeOwned,
+{
+    let mut deserializer = Deserializer::from_reader(reader)?;
+
+    let result = T::deserialize(&mut deserializer).and_then(|t| {
+        deserializer.parser.parse_eof()?;
+        Ok(t)
+    });
+
+    deserializer.parser.check_io(result)
+}
+
+/// Lazily deserialize the data rows of an ARFF formatted string as instances of type `T`.
+///
+/// The header is parsed immediately; the rows are parsed one at a time as the returned iterator
+/// is advanced.
+pub fn rows<'a, T>(s: &'a str) -> Result<StreamDeserializer<'a, T>>
+where
+    T: Deserialize<'a>,
+{
+    Ok(Deserializer::from_str(s)?.into_rows())
+}
+
+/// Deserialize the data rows of an ARFF formatted string as instances of type `T`, skipping rows
+/// that fail to parse.
+///
+/// Returns the rows that could be parsed together with a diagnostic for each skipped row. Errors
+/// in the header still fail as a whole.
+pub fn from_str_lenient<'a, T>(s: &'a str) -> Result<(Vec<T>, Vec<Diagnostic>)>
+where
+    T: Deserialize<'a>,
+{
+    collect_lenient(rows(s)?)
+}
+
+/// Deserialize the data rows from a reader of ARFF formatted text as instances of type `T`,
+/// skipping rows that fail to parse.
+///
+/// Like `from_str_lenient`, but read errors fail as a whole.
+pub fn from_reader_lenient<R, T>(reader: R) -> Result<(Vec<T>, Vec<Diagnostic>)>
+where
+    R: BufRead,
+    T: DeserializeOwned,
+{
+    collect_lenient(Deserializer::from_reader(reader)?.into_rows())
+}
+
+fn collect_lenient<T, I>(rows: I) -> Result<(Vec<T>, Vec<Diagnostic>)>
+where
+    I: Iterator<Item = Result<T>>,
+{
+    let mut data = Vec::new();
+    let mut diagnostics = Vec::new();
+    for row in rows {
+        match row {
+            Ok(row) => data.push(row),
+            Err(Error::Row { line, error }) => diagnostics.push(Diagnostic::new(line, *error)),
+            Err(e) => return Err(e),
+        }
+    }
+    Ok((data, diagnostics))
+}
+
 /// Deserialize an instance of sequence type `T` from an ARFF formatted string, to obtain a flat
 /// representation of the data.
 pub fn flat_from_str<'a, T>(s: &'a str) -> Result<T>
@@ -57,6 +159,87 @@ impl<'de> Deserializer<'de> {
 
         Ok(Deserializer { parser, header })
     }
+
+    pub fn from_reader<R: BufRead + 'de>(reader: R) -> Result<Self> {
+        let mut parser = Parser::from_reader(reader);
+        let header = parser.parse_header();
+        let header = parser.check_io(header)?;
+
+        Ok(Deserializer { parser, header })
+    }
+
+    /// The header of the data set, which has already been parsed.
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    /// Check the values against the declared attribute types, like `from_str_strict`.
+    pub fn set_strict(&mut self, strict: bool) {
+        self.parser.set_strict(strict);
+    }
+
+    /// Turn the deserializer into an iterator over the data rows.
+    pub fn into_rows<T>(self) -> StreamDeserializer<'de, T>
+    where
+        T: Deserialize<'de>,
+    {
+        StreamDeserializer {
+            de: self,
+            finished: false,
+            output: PhantomData,
+        }
+    }
+}
+
+/// Iterator that deserializes the data rows of an ARFF data set one at a time.
+///
+/// Errors in a row are reported as `Error::Row`, together with the line the row starts on.
+/// Iteration then resumes with the next line, so the caller may decide to skip bad rows.
+pub struct StreamDeserializer<'de, T> {
+    de: Deserializer<'de>,
+    finished: bool,
+    output: PhantomData<T>,
+}
+
+impl<'de, T> Iterator for StreamDeserializer<'de, T>
+where
+    T: Deserialize<'de>,
+{
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Result<T>> {
+        if self.finished {
+            return None;
+        }
+
+        self.de.parser.skip_empty();
+        if self.de.parser.is_eof() {
+            self.finished = true;
+            return self.de.parser.check_io(Ok(())).err().map(Err);
+        }
+
+        let line = self.de.parser.pos().line();
+        let result = DataRows::new(&mut self.de).next_element();
+
+        match self.de.parser.check_io(result) {
+            Ok(Some(row)) => Some(Ok(row)),
+            Ok(None) => {
+                self.finished = true;
+                None
+            }
+            Err(e @ Error::Io(_, _)) => {
+                self.finished = true;
+                Some(Err(e))
+            }
+            Err(e) => {
+                self.de.parser.skip_line();
+                Some(Err(Error::Row {
+                    line,
+                    error: Box::new(e),
+                }))
+            }
+        }
+    }
 }
 
 impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
@@ -282,21 +465,221 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     }
 }
 
+/// The value implied by a sparse row for an omitted column
+enum SparseDefault<'a> {
+    /// numeric zero
+    Zero,
+    /// the Unix epoch
+    Date(&'a DateFormat),
+    /// text, e.g. the first category of a nominal attribute
+    Text(&'a str),
+}
+
 /// Deserialize an ARFF data row into a Rust data structure.
 struct RowDeserializer<'de: 'a, 'a> {
     parser: &'a mut Parser<'de>,
     header: &'a Header,
     current_column: usize,
+    sparse: bool,
+    next_sparse_index: Option<usize>,
 }
 
 impl<'de, 'a> RowDeserializer<'de, 'a> {
-    fn new(de: &'a mut Deserializer<'de>) -> Self {
-        RowDeserializer {
+    fn new(de: &'a mut Deserializer<'de>) -> Result<Self> {
+        let sparse = de.parser.parse_is_sparse();
+        let next_sparse_index = if sparse {
+            de.parser.parse_sparse_index()?
+        } else {
+            None
+        };
+
+        Ok(RowDeserializer {
             parser: &mut de.parser,
             header: &mut de.header,
             current_column: 0,
+            sparse,
+            next_sparse_index,
+        })
+    }
+
+    /// Make sure all values of the row have been consumed
+    fn end(&mut self) -> Result<()> {
+        if self.next_sparse_index.is_some() {
+            return Err(Error::Expected(self.parser.pos(), "end of sparse row"));
+        }
+        Ok(())
+    }
+
+    /// Is the value of the current column omitted from a sparse row?
+    fn is_sparse_default(&self) -> bool {
+        self.sparse && self.next_sparse_index != Some(self.current_column)
+    }
+
+    /// The value implied by a sparse row for the current column
+    fn sparse_default(&self) -> SparseDefault<'a> {
+        match self.header.attrs.get(self.current_column) {
+            Some(&Attribute {
+                dtype: DType::Nominal(ref names),
+                ..
+            }) if !names.is_empty() => SparseDefault::Text(&names[0]),
+            Some(&Attribute {
+                dtype: DType::String,
+                ..
+            })
+            | Some(&Attribute {
+                dtype: DType::Relational(_),
+                ..
+            }) => SparseDefault::Text(""),
+            Some(&Attribute {
+                dtype: DType::Date(ref format),
+                ..
+            }) => SparseDefault::Date(format),
+            _ => SparseDefault::Zero,
+        }
+    }
+
+    /// Move on to the next value in a sparse row
+    fn advance_sparse(&mut self) -> Result<()> {
+        if self.sparse {
+            self.parser.parse_sparse_delimiter()?;
+            let pos = self.parser.pos();
+            self.next_sparse_index = self.parser.parse_sparse_index()?;
+            match self.next_sparse_index {
+                Some(idx) if idx <= self.current_column => {
+                    return Err(Error::Expected(
+                        pos,
+                        "sparse column indices in ascending order",
+                    ))
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Parse the value of the current column
+    ///
+    /// Values omitted from a sparse row are parsed from their default representation.
+    fn parse_value<T, F>(&mut self, parse: F) -> Result<T>
+    where
+        T: Default,
+        F: for<'p> FnOnce(&mut Parser<'p>) -> Result<T>,
+    {
+        if self.is_sparse_default() {
+            return match self.sparse_default() {
+                SparseDefault::Text(text) => {
+                    let pos = self.parser.pos();
+                    parse(&mut Parser::new(text)).map_err(|e| self.attribute_error(pos, e))
+                }
+                SparseDefault::Zero | SparseDefault::Date(_) => Ok(T::default()),
+            };
+        }
+        self.parse_present(parse)
+    }
+
+    /// Parse the text of the current column, which borrows the value implied by a sparse row
+    fn parse_text(&mut self) -> Result<Cow<'a, str>> {
+        if self.is_sparse_default() {
+            return Ok(match self.sparse_default() {
+                SparseDefault::Text(text) => Cow::Borrowed(text),
+                SparseDefault::Zero => Cow::Borrowed("0"),
+                SparseDefault::Date(format) => Cow::Owned(format.format(0)),
+            });
+        }
+        self.parse_present(|p| p.parse_string()).map(Cow::Owned)
+    }
+
+    /// Parse the value of the current column, which is not omitted from a sparse row
+    fn parse_present<T, F>(&mut self, parse: F) -> Result<T>
+    where
+        F: FnOnce(&mut Parser<'de>) -> Result<T>,
+    {
+        let pos = self.parser.pos();
+        if self.parser.parse_is_missing() {
+            return Err(self.attribute_error(pos, Error::UnexpectedMissingValue));
+        }
+        let value = parse(self.parser).map_err(|e| self.attribute_error(pos, e))?;
+        self.advance_sparse()?;
+        Ok(value)
+    }
+
+    /// Attach the name of the current attribute and the position of its value to an error
+    fn attribute_error(&self, pos: TextPos, error: Error) -> Error {
+        match (self.header.attrs.get(self.current_column), error) {
+            (_, e @ Error::Io(_, _)) | (None, e) => e,
+            (Some(attr), e) => Error::Attribute {
+                name: attr.name.clone(),
+                pos: Some(pos),
+                error: Box::new(e),
+            },
+        }
+    }
+
+    /// Check for a missing value in the current column
+    fn parse_is_missing(&mut self) -> Result<bool> {
+        if self.is_sparse_default() || !self.parser.parse_is_missing() {
+            return Ok(false);
+        }
+        self.advance_sparse()?;
+        Ok(true)
+    }
+
+    /// Parse the delimiter between two columns
+    ///
+    /// Sparse rows delimit their values while parsing them.
+    fn parse_column_delimiter(&mut self) -> Result<()> {
+        if self.sparse {
+            Ok(())
+        } else {
+            self.parser.parse_column_delimiter()
         }
     }
+
+    /// Are we at the end of the row?
+    ///
+    /// Rows end after the last declared attribute, so that a trailing instance weight is not
+    /// mistaken for another column. Without declared attributes, rows end at the newline.
+    fn check_row_delimiter(&mut self) -> bool {
+        if self.sparse || !self.header.attrs.is_empty() {
+            self.current_column >= self.header.attrs.len()
+        } else {
+            self.parser.check_row_delimiter()
+        }
+    }
+
+    /// parse a floating point number, which must be whole in `INTEGER` columns in strict mode
+    fn parse_float(&mut self) -> Result<f64> {
+        let pos = self.parser.pos();
+        let value = self.parse_value(|p| p.parse_float())?;
+        let integer = self
+            .header
+            .attrs
+            .get(self.current_column)
+            .map(|attr| &attr.dtype)
+            == Some(&DType::Integer);
+        if integer && self.parser.is_strict() && value.fract() != 0.0 {
+            let error = Error::ExpectedIntegerValue(pos, value.to_string());
+            return Err(self.attribute_error(pos, error));
+        }
+        Ok(value)
+    }
+
+    /// parse a date according to the format declared for the current column
+    fn parse_date(&mut self) -> Result<i64> {
+        if self.is_sparse_default() {
+            return Ok(0);
+        }
+        let pos = self.parser.pos();
+        let s = self.parse_present(|p| p.parse_string())?;
+        let millis = match self.header.attrs.get(self.current_column) {
+            Some(&Attribute {
+                dtype: DType::Date(ref format),
+                ..
+            }) => format.parse(&s),
+            _ => DateFormat::default().parse(&s),
+        };
+        millis.ok_or_else(|| self.attribute_error(pos, Error::InvalidDate(pos, s)))
+    }
 }
 
 impl<'de, 'a, 'b> de::Deserializer<'de> for &'b mut RowDeserializer<'de, 'a> {
@@ -313,77 +696,77 @@ impl<'de, 'a, 'b> de::Deserializer<'de> for &'b mut RowDeserializer<'de, 'a> {
     where
         V: Visitor<'de>,
     {
-        visitor.visit_bool(self.parser.parse_bool()?)
+        visitor.visit_bool(self.parse_value(|p| p.parse_bool())?)
     }
 
     fn deserialize_i8<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        visitor.visit_i8(self.parser.parse_i8()?)
+        visitor.visit_i8(self.parse_value(|p| p.parse_i8())?)
     }
 
     fn deserialize_i16<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        visitor.visit_i16(self.parser.parse_i16()?)
+        visitor.visit_i16(self.parse_value(|p| p.parse_i16())?)
     }
 
     fn deserialize_i32<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        visitor.visit_i32(self.parser.parse_i32()?)
+        visitor.visit_i32(self.parse_value(|p| p.parse_i32())?)
     }
 
     fn deserialize_i64<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        visitor.visit_i64(self.parser.parse_i64()?)
+        visitor.visit_i64(self.parse_value(|p| p.parse_i64())?)
     }
 
     fn deserialize_u8<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        visitor.visit_u8(self.parser.parse_u8()?)
+        visitor.visit_u8(self.parse_value(|p| p.parse_u8())?)
     }
 
     fn deserialize_u16<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        visitor.visit_u16(self.parser.parse_u16()?)
+        visitor.visit_u16(self.parse_value(|p| p.parse_u16())?)
     }
 
     fn deserialize_u32<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        visitor.visit_u32(self.parser.parse_u32()?)
+        visitor.visit_u32(self.parse_value(|p| p.parse_u32())?)
     }
 
     fn deserialize_u64<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        visitor.visit_u64(self.parser.parse_u64()?)
+        visitor.visit_u64(self.parse_value(|p| p.parse_u64())?)
     }
 
     fn deserialize_f32<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        visitor.visit_f32(self.parser.parse_float()? as f32)
+        visitor.visit_f32(self.parse_float()? as f32)
     }
 
     fn deserialize_f64<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        visitor.visit_f64(self.parser.parse_float()?)
+        visitor.visit_f64(self.parse_float()?)
     }
 
     fn deserialize_char<V>(self, _visitor: V) -> Result<V::Value>
@@ -397,7 +780,19 @@ impl<'de, 'a, 'b> de::Deserializer<'de> for &'b mut RowDeserializer<'de, 'a> {
     where
         V: Visitor<'de>,
     {
-        visitor.visit_str(&self.parser.parse_string()?)
+        if self.is_sparse_default() {
+            return match self.parse_text()? {
+                Cow::Borrowed(s) => visitor.visit_str(s),
+                Cow::Owned(s) => visitor.visit_string(s),
+            };
+        }
+        let pos = self.parser.pos();
+        let value = match self.parse_present(|p| p.parse_str())? {
+            Cow::Borrowed(s) => visitor.visit_borrowed_str(s),
+            Cow::Owned(s) => visitor.visit_string(s),
+        };
+        // e.g. an escaped string for a `&str`
+        value.map_err(|e| self.attribute_error(pos, e))
     }
 
     fn deserialize_string<V>(self, visitor: V) -> Result<V::Value>
@@ -425,7 +820,7 @@ impl<'de, 'a, 'b> de::Deserializer<'de> for &'b mut RowDeserializer<'de, 'a> {
     where
         V: Visitor<'de>,
     {
-        if self.parser.parse_is_missing() {
+        if self.parse_is_missing()? {
             visitor.visit_none()
         } else {
             visitor.visit_some(self)
@@ -446,10 +841,14 @@ impl<'de, 'a, 'b> de::Deserializer<'de> for &'b mut RowDeserializer<'de, 'a> {
         unimplemented!()
     }
 
-    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
+        if name == DATE_TOKEN {
+            let millis: de::value::I64Deserializer<Error> = self.parse_date()?.into_deserializer();
+            return visitor.visit_newtype_struct(millis);
+        }
         visitor.visit_newtype_struct(self)
     }
 
@@ -457,6 +856,33 @@ impl<'de, 'a, 'b> de::Deserializer<'de> for &'b mut RowDeserializer<'de, 'a> {
     where
         V: Visitor<'de>,
     {
+        if let Some(&Attribute {
+            ref name,
+            dtype: DType::Relational(ref attrs),
+        }) = self.header.attrs.get(self.current_column)
+        {
+            let header = Header {
+                name: name.clone(),
+                attrs: attrs.clone(),
+                relation_comments: Vec::new(),
+                comments: Vec::new(),
+            };
+            let pos = self.parser.pos();
+            let parser = if self.is_sparse_default() {
+                Parser::from_string(String::new())
+            } else {
+                self.parse_present(|p| p.parse_nested_rows())?
+            };
+
+            let mut de = Deserializer { parser, header };
+            return visitor
+                .visit_seq(DataRows::new(&mut de))
+                .and_then(|value| {
+                    de.parser.parse_eof()?;
+                    Ok(value)
+                })
+                .map_err(|e| self.attribute_error(pos, e));
+        }
         visitor.visit_seq(DataCols::new(self))
     }
 
@@ -469,13 +895,16 @@ impl<'de, 'a, 'b> de::Deserializer<'de> for &'b mut RowDeserializer<'de, 'a> {
 
     fn deserialize_tuple_struct<V>(
         self,
-        _name: &'static str,
+        name: &'static str,
         len: usize,
         visitor: V,
     ) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
+        if name == WEIGHTED_TOKEN {
+            return visitor.visit_seq(WeightedRow::new(self));
+        }
         self.deserialize_tuple(len, visitor)
     }
 
@@ -507,7 +936,11 @@ impl<'de, 'a, 'b> de::Deserializer<'de> for &'b mut RowDeserializer<'de, 'a> {
     where
         V: Visitor<'de>,
     {
-        visitor.visit_enum(self.parser.parse_string()?.into_deserializer())
+        let pos = self.parser.pos();
+        let value = self.parse_text()?;
+        visitor
+            .visit_enum(value.as_ref().into_deserializer())
+            .map_err(|e| self.attribute_error(pos, e))
     }
 
     fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value>
@@ -549,9 +982,13 @@ impl<'de, 'a> SeqAccess<'de> for DataRows<'a, 'de> {
         }
 
         let value = {
-            let mut de = RowDeserializer::new(&mut self.de);
-            seed.deserialize(&mut de)?
+            let mut de = RowDeserializer::new(&mut self.de)?;
+            let value = seed.deserialize(&mut de)?;
+            de.end()?;
+            value
         };
+        // instance weights are only of interest to `Weighted` rows
+        self.de.parser.parse_weight()?;
         self.de.parser.parse_row_delimiter()?;
         Ok(Some(value))
     }
@@ -585,10 +1022,13 @@ impl<'a, 'b, 'de> MapAccess<'de> for DataCols<'a, 'b, 'de> {
     where
         V: DeserializeSeed<'de>,
     {
+        let last_idx = self.de.current_column;
         let value = seed.deserialize(&mut *self.de)?;
-        self.de.current_column += 1;
+        if last_idx == self.de.current_column {
+            self.de.current_column += 1;
+        }
         if self.de.current_column < self.de.header.attrs.len() {
-            self.de.parser.parse_column_delimiter()?;
+            self.de.parse_column_delimiter()?;
         }
         Ok(value)
     }
@@ -601,14 +1041,17 @@ impl<'de, 'a, 'b> SeqAccess<'de> for DataCols<'a, 'b, 'de> {
     where
         T: DeserializeSeed<'de>,
     {
-        if self.de.parser.check_row_delimiter() {
+        if self.de.check_row_delimiter() {
             return Ok(None);
         }
 
+        let last_idx = self.de.current_column;
         let value = seed.deserialize(&mut *self.de)?;
-        self.de.current_column += 1;
+        if last_idx == self.de.current_column {
+            self.de.current_column += 1;
+        }
         if self.de.current_column < self.de.header.attrs.len() {
-            self.de.parser.parse_column_delimiter()?;
+            self.de.parse_column_delimiter()?;
         }
         Ok(Some(value))
     }
@@ -635,22 +1078,60 @@ impl<'de, 'a, 'b> SeqAccess<'de> for DataColsTuple<'a, 'b, 'de> {
     where
         T: DeserializeSeed<'de>,
     {
-        if self.de.parser.check_row_delimiter() {
+        if self.de.check_row_delimiter() {
             return Ok(None);
         }
 
+        let last_idx = self.de.current_column;
         let value = seed.deserialize(&mut *self.de)?;
+        if last_idx == self.de.current_column {
+            self.de.current_column += 1;
+        }
 
-        self.de.current_column += 1;
         self.n_elements_to_go -= 1;
 
         if self.n_elements_to_go > 0 {
-            self.de.parser.parse_column_delimiter()?;
+            self.de.parse_column_delimiter()?;
         }
         Ok(Some(value))
     }
 }
 
+/// Deserialize a data row followed by its instance weight.
+struct WeightedRow<'a, 'b: 'a, 'de: 'b> {
+    de: &'a mut RowDeserializer<'de, 'b>,
+    n_elements_done: usize,
+}
+
+impl<'a, 'b, 'de> WeightedRow<'a, 'b, 'de> {
+    fn new(de: &'a mut RowDeserializer<'de, 'b>) -> Self {
+        WeightedRow {
+            de,
+            n_elements_done: 0,
+        }
+    }
+}
+
+impl<'de, 'a, 'b> SeqAccess<'de> for WeightedRow<'a, 'b, 'de> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        self.n_elements_done += 1;
+        match self.n_elements_done {
+            1 => seed.deserialize(&mut *self.de).map(Some),
+            2 => {
+                self.de.end()?;
+                let weight = self.de.parser.parse_weight()?.unwrap_or(DEFAULT_WEIGHT);
+                seed.deserialize(weight.into_deserializer()).map(Some)
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
 /// Deserialize an ARFF data set into a flat Rust sequence.
 pub struct FlatDeserializer<'de> {
     parser: Parser<'de>,
@@ -694,7 +1175,9 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut FlatDeserializer<'de> {
     {
         let pos = self.parser.pos();
         match self.header.attrs[self.current_col].dtype {
-            DType::Numeric => visitor.visit_i8(self.parser.parse_i8()?),
+            DType::Numeric | DType::Integer | DType::Real => {
+                visitor.visit_i8(self.parser.parse_i8()?)
+            }
             DType::Nominal(ref names) => {
                 let name = self.parser.parse_string()?;
                 match names.iter().position(|n| n == &name) {
@@ -703,6 +1186,8 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut FlatDeserializer<'de> {
                 }
             }
             DType::String => Err(Error::UnsupportedColumnType(pos, "String".to_owned())),
+            DType::Relational(_) => Err(Error::UnsupportedColumnType(pos, "Relational".to_owned())),
+            DType::Date(_) => Err(Error::UnsupportedColumnType(pos, "Date".to_owned())),
         }
     }
 
@@ -712,7 +1197,9 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut FlatDeserializer<'de> {
     {
         let pos = self.parser.pos();
         match self.header.attrs[self.current_col].dtype {
-            DType::Numeric => visitor.visit_i16(self.parser.parse_i16()?),
+            DType::Numeric | DType::Integer | DType::Real => {
+                visitor.visit_i16(self.parser.parse_i16()?)
+            }
             DType::Nominal(ref names) => {
                 let name = self.parser.parse_string()?;
                 match names.iter().position(|n| n == &name) {
@@ -721,6 +1208,8 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut FlatDeserializer<'de> {
                 }
             }
             DType::String => Err(Error::UnsupportedColumnType(pos, "String".to_owned())),
+            DType::Relational(_) => Err(Error::UnsupportedColumnType(pos, "Relational".to_owned())),
+            DType::Date(_) => Err(Error::UnsupportedColumnType(pos, "Date".to_owned())),
         }
     }
 
@@ -730,7 +1219,9 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut FlatDeserializer<'de> {
     {
         let pos = self.parser.pos();
         match self.header.attrs[self.current_col].dtype {
-            DType::Numeric => visitor.visit_i32(self.parser.parse_i32()?),
+            DType::Numeric | DType::Integer | DType::Real => {
+                visitor.visit_i32(self.parser.parse_i32()?)
+            }
             DType::Nominal(ref names) => {
                 let name = self.parser.parse_string()?;
                 match names.iter().position(|n| n == &name) {
@@ -739,6 +1230,8 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut FlatDeserializer<'de> {
                 }
             }
             DType::String => Err(Error::UnsupportedColumnType(pos, "String".to_owned())),
+            DType::Relational(_) => Err(Error::UnsupportedColumnType(pos, "Relational".to_owned())),
+            DType::Date(_) => Err(Error::UnsupportedColumnType(pos, "Date".to_owned())),
         }
     }
 
@@ -748,7 +1241,9 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut FlatDeserializer<'de> {
     {
         let pos = self.parser.pos();
         match self.header.attrs[self.current_col].dtype {
-            DType::Numeric => visitor.visit_i64(self.parser.parse_i64()?),
+            DType::Numeric | DType::Integer | DType::Real => {
+                visitor.visit_i64(self.parser.parse_i64()?)
+            }
             DType::Nominal(ref names) => {
                 let name = self.parser.parse_string()?;
                 match names.iter().position(|n| n == &name) {
@@ -757,6 +1252,14 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut FlatDeserializer<'de> {
                 }
             }
             DType::String => Err(Error::UnsupportedColumnType(pos, "String".to_owned())),
+            DType::Relational(_) => Err(Error::UnsupportedColumnType(pos, "Relational".to_owned())),
+            DType::Date(ref format) => {
+                let value = self.parser.parse_string()?;
+                match format.parse(&value) {
+                    Some(millis) => visitor.visit_i64(millis),
+                    None => Err(Error::InvalidDate(pos, value)),
+                }
+            }
         }
     }
 
@@ -766,7 +1269,9 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut FlatDeserializer<'de> {
     {
         let pos = self.parser.pos();
         match self.header.attrs[self.current_col].dtype {
-            DType::Numeric => visitor.visit_u8(self.parser.parse_u8()?),
+            DType::Numeric | DType::Integer | DType::Real => {
+                visitor.visit_u8(self.parser.parse_u8()?)
+            }
             DType::Nominal(ref names) => {
                 let name = self.parser.parse_string()?;
                 match names.iter().position(|n| n == &name) {
@@ -775,6 +1280,8 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut FlatDeserializer<'de> {
                 }
             }
             DType::String => Err(Error::UnsupportedColumnType(pos, "String".to_owned())),
+            DType::Relational(_) => Err(Error::UnsupportedColumnType(pos, "Relational".to_owned())),
+            DType::Date(_) => Err(Error::UnsupportedColumnType(pos, "Date".to_owned())),
         }
     }
 
@@ -784,7 +1291,9 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut FlatDeserializer<'de> {
     {
         let pos = self.parser.pos();
         match self.header.attrs[self.current_col].dtype {
-            DType::Numeric => visitor.visit_u16(self.parser.parse_u16()?),
+            DType::Numeric | DType::Integer | DType::Real => {
+                visitor.visit_u16(self.parser.parse_u16()?)
+            }
             DType::Nominal(ref names) => {
                 let name = self.parser.parse_string()?;
                 match names.iter().position(|n| n == &name) {
@@ -793,6 +1302,8 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut FlatDeserializer<'de> {
                 }
             }
             DType::String => Err(Error::UnsupportedColumnType(pos, "String".to_owned())),
+            DType::Relational(_) => Err(Error::UnsupportedColumnType(pos, "Relational".to_owned())),
+            DType::Date(_) => Err(Error::UnsupportedColumnType(pos, "Date".to_owned())),
         }
     }
 
@@ -802,7 +1313,9 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut FlatDeserializer<'de> {
     {
         let pos = self.parser.pos();
         match self.header.attrs[self.current_col].dtype {
-            DType::Numeric => visitor.visit_u32(self.parser.parse_u32()?),
+            DType::Numeric | DType::Integer | DType::Real => {
+                visitor.visit_u32(self.parser.parse_u32()?)
+            }
             DType::Nominal(ref names) => {
                 let name = self.parser.parse_string()?;
                 match names.iter().position(|n| n == &name) {
@@ -811,6 +1324,8 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut FlatDeserializer<'de> {
                 }
             }
             DType::String => Err(Error::UnsupportedColumnType(pos, "String".to_owned())),
+            DType::Relational(_) => Err(Error::UnsupportedColumnType(pos, "Relational".to_owned())),
+            DType::Date(_) => Err(Error::UnsupportedColumnType(pos, "Date".to_owned())),
         }
     }
 
@@ -820,7 +1335,9 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut FlatDeserializer<'de> {
     {
         let pos = self.parser.pos();
         match self.header.attrs[self.current_col].dtype {
-            DType::Numeric => visitor.visit_u64(self.parser.parse_u64()?),
+            DType::Numeric | DType::Integer | DType::Real => {
+                visitor.visit_u64(self.parser.parse_u64()?)
+            }
             DType::Nominal(ref names) => {
                 let name = self.parser.parse_string()?;
                 match names.iter().position(|n| n == &name) {
@@ -829,6 +1346,8 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut FlatDeserializer<'de> {
                 }
             }
             DType::String => Err(Error::UnsupportedColumnType(pos, "String".to_owned())),
+            DType::Relational(_) => Err(Error::UnsupportedColumnType(pos, "Relational".to_owned())),
+            DType::Date(_) => Err(Error::UnsupportedColumnType(pos, "Date".to_owned())),
         }
     }
 
@@ -838,7 +1357,9 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut FlatDeserializer<'de> {
     {
         let pos = self.parser.pos();
         match self.header.attrs[self.current_col].dtype {
-            DType::Numeric => visitor.visit_f32(self.parser.parse_float()? as f32),
+            DType::Numeric | DType::Integer | DType::Real => {
+                visitor.visit_f32(self.parser.parse_float()? as f32)
+            }
             DType::Nominal(ref names) => {
                 let name = self.parser.parse_string()?;
                 match names.iter().position(|n| n == &name) {
@@ -847,6 +1368,8 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut FlatDeserializer<'de> {
                 }
             }
             DType::String => Err(Error::UnsupportedColumnType(pos, "String".to_owned())),
+            DType::Relational(_) => Err(Error::UnsupportedColumnType(pos, "Relational".to_owned())),
+            DType::Date(_) => Err(Error::UnsupportedColumnType(pos, "Date".to_owned())),
         }
     }
 
@@ -856,7 +1379,9 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut FlatDeserializer<'de> {
     {
         let pos = self.parser.pos();
         match self.header.attrs[self.current_col].dtype {
-            DType::Numeric => visitor.visit_f64(self.parser.parse_float()?),
+            DType::Numeric | DType::Integer | DType::Real => {
+                visitor.visit_f64(self.parser.parse_float()?)
+            }
             DType::Nominal(ref names) => {
                 let name = self.parser.parse_string()?;
                 match names.iter().position(|n| n == &name) {
@@ -865,6 +1390,14 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut FlatDeserializer<'de> {
                 }
             }
             DType::String => Err(Error::UnsupportedColumnType(pos, "String".to_owned())),
+            DType::Relational(_) => Err(Error::UnsupportedColumnType(pos, "Relational".to_owned())),
+            DType::Date(ref format) => {
+                let value = self.parser.parse_string()?;
+                match format.parse(&value) {
+                    Some(millis) => visitor.visit_f64(millis as f64),
+                    None => Err(Error::InvalidDate(pos, value)),
+                }
+            }
         }
     }
 
@@ -1220,60 +1753,66 @@ fn test_comments() {
 fn test_ranges() {
     use parser::TextPos;
     use std::{i64, u64};
-    assert_eq!(from_str("@RELATION x @DATA\n 0, 255"), Ok([[0u8, 255]]));
+    assert_eq!(from_str("@RELATION x\n@DATA\n 0, 255"), Ok([[0u8, 255]]));
     assert_eq!(
-        from_str::<[[u8; 1]; 1]>("@RELATION x @DATA\n  -1"),
-        Err(Error::ExpectedUnsignedValue(TextPos::new(2, 3)))
+        from_str::<[[u8; 1]; 1]>("@RELATION x\n@DATA\n  -1"),
+        Err(Error::ExpectedUnsignedValue(
+            TextPos::new(3, 3),
+            "-1".to_owned()
+        ))
     );
     assert_eq!(
-        from_str::<[[u8; 1]; 1]>("@RELATION x @DATA\n 256"),
-        Err(Error::NumericRange(TextPos::new(2, 2), 0, 255))
+        from_str::<[[u8; 1]; 1]>("@RELATION x\n@DATA\n 256"),
+        Err(Error::NumericRange(TextPos::new(3, 2), 0, 255))
     );
 
     assert_eq!(
-        from_str("@RELATION x @DATA\n -128, 127"),
+        from_str("@RELATION x\n@DATA\n -128, 127"),
         Ok([[-128i8, 127]])
     );
     assert_eq!(
-        from_str::<[[i8; 1]; 1]>("@RELATION x @DATA\n -129"),
-        Err(Error::NumericRange(TextPos::new(2, 2), -128, 127))
+        from_str::<[[i8; 1]; 1]>("@RELATION x\n@DATA\n -129"),
+        Err(Error::NumericRange(TextPos::new(3, 2), -128, 127))
     );
     assert_eq!(
-        from_str::<[[i8; 1]; 1]>("@RELATION x @DATA\n  128"),
-        Err(Error::NumericRange(TextPos::new(2, 3), -128, 127))
+        from_str::<[[i8; 1]; 1]>("@RELATION x\n@DATA\n  128"),
+        Err(Error::NumericRange(TextPos::new(3, 3), -128, 127))
     );
 
     assert_eq!(
-        from_str("@RELATION x @DATA\n -9223372036854775808, 9223372036854775807"),
+        from_str("@RELATION x\n@DATA\n -9223372036854775808, 9223372036854775807"),
         Ok([[i64::MIN, i64::MAX]])
     );
     assert_eq!(
-        from_str::<[[i64; 1]; 1]>("@RELATION x @DATA\n -9223372036854775809"),
-        Err(Error::NumericRange(TextPos::new(2, 2), i64::MIN, i64::MAX))
+        from_str::<[[i64; 1]; 1]>("@RELATION x\n@DATA\n -9223372036854775809"),
+        Err(Error::NumericRange(TextPos::new(3, 2), i64::MIN, i64::MAX))
     );
     assert_eq!(
-        from_str::<[[i64; 1]; 1]>("@RELATION x @DATA\n  9223372036854775808"),
-        Err(Error::NumericRange(TextPos::new(2, 3), i64::MIN, i64::MAX))
+        from_str::<[[i64; 1]; 1]>("@RELATION x\n@DATA\n  9223372036854775808"),
+        Err(Error::NumericRange(TextPos::new(3, 3), i64::MIN, i64::MAX))
     );
 
     assert_eq!(
-        from_str("@RELATION x @DATA\n 0, 18446744073709551615"),
+        from_str("@RELATION x\n@DATA\n 0, 18446744073709551615"),
         Ok([[u64::MIN, u64::MAX]])
     );
     assert_eq!(
-        from_str::<[[u64; 1]; 1]>("@RELATION x @DATA\n                   -1"),
-        Err(Error::ExpectedUnsignedValue(TextPos::new(2, 20)))
+        from_str::<[[u64; 1]; 1]>("@RELATION x\n@DATA\n                   -1"),
+        Err(Error::ExpectedUnsignedValue(
+            TextPos::new(3, 20),
+            "-1".to_owned()
+        ))
     );
     assert_eq!(
-        from_str::<[[u64; 1]; 1]>("@RELATION x @DATA\n 18446744073709551616"),
-        Err(Error::NumericOverflow(TextPos::new(2, 2)))
+        from_str::<[[u64; 1]; 1]>("@RELATION x\n@DATA\n 18446744073709551616"),
+        Err(Error::NumericOverflow(TextPos::new(3, 2)))
     );
 }
 
 #[test]
 fn test_missing() {
     assert_eq!(
-        from_str("@RELATION x @DATA\n 1\n ?\n 3"),
+        from_str("@RELATION x\n@DATA\n 1\n ?\n 3"),
         Ok([[Some(1)], [None], [Some(3)]])
     );
 }
@@ -1384,3 +1923,495 @@ fn test_flat() {
     let res: Vec<u8> = flat_from_str(input).unwrap();
     assert_eq!(res, vec![42, 9, 8, 7, 7, 5, 3, 2]);
 }
+
+#[test]
+fn test_date() {
+    use date::Date;
+
+    let input = "@RELATION Data
+
+@ATTRIBUTE id NUMERIC
+@ATTRIBUTE ts DATE \"yyyy-MM-dd HH:mm:ss\"
+@ATTRIBUTE raw DATE
+
+@DATA
+1, '2001-04-03 12:12:12', 1970-01-01T00:00:01
+2, ?, '2018-07-10T23:01:02'";
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Row {
+        id: u8,
+        ts: Option<Date>,
+        raw: String,
+    }
+
+    let res: Vec<Row> = from_str(input).unwrap();
+    assert_eq!(
+        res,
+        vec![
+            Row {
+                id: 1,
+                ts: Some(Date::from_millis(986_299_932_000)),
+                raw: "1970-01-01T00:00:01".to_owned(),
+            },
+            Row {
+                id: 2,
+                ts: None,
+                raw: "2018-07-10T23:01:02".to_owned(),
+            },
+        ]
+    );
+
+    assert_eq!(
+        from_str::<Vec<(u8, Date, Date)>>(input),
+        Err(Error::Attribute {
+            name: "ts".to_owned(),
+            pos: Some(TextPos::new(9, 4)),
+            error: Box::new(Error::UnexpectedMissingValue),
+        })
+    );
+}
+
+#[test]
+fn test_sparse() {
+    use date::Date;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    enum Class {
+        No,
+        Yes,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Row {
+        a: f64,
+        b: Option<u8>,
+        word: String,
+        class: Class,
+    }
+
+    let input = "@RELATION Data
+
+@ATTRIBUTE a NUMERIC
+@ATTRIBUTE b NUMERIC
+@ATTRIBUTE word STRING
+@ATTRIBUTE class {No, Yes}
+
+@DATA
+{0 1.5, 3 Yes}
+{}
+{1 ?, 2 'hello world'}
+1, 2, dense, No";
+
+    let res: Vec<Row> = from_str(input).unwrap();
+    assert_eq!(
+        res,
+        vec![
+            Row {
+                a: 1.5,
+                b: Some(0),
+                word: "".to_owned(),
+                class: Class::Yes
+            },
+            Row {
+                a: 0.0,
+                b: Some(0),
+                word: "".to_owned(),
+                class: Class::No
+            },
+            Row {
+                a: 0.0,
+                b: None,
+                word: "hello world".to_owned(),
+                class: Class::No
+            },
+            Row {
+                a: 1.0,
+                b: Some(2),
+                word: "dense".to_owned(),
+                class: Class::No
+            },
+        ]
+    );
+
+    let res: Vec<(f64, (Option<u8>, String), Class)> = from_str(input).unwrap();
+    assert_eq!(res[0], (1.5, (Some(0), "".to_owned()), Class::Yes));
+    assert_eq!(res[2], (0.0, (None, "hello world".to_owned()), Class::No));
+
+    // omitted values are taken from the header, without parsing them
+    let input = "@RELATION Data
+@ATTRIBUTE city {'New York', Boston}
+@ATTRIBUTE day DATE
+@ATTRIBUTE flag {1, 0}
+@DATA
+{}
+";
+    let res: Vec<(String, Date, u8)> = from_str(input).unwrap();
+    assert_eq!(res[0].0, "New York");
+    assert_eq!(res[0].1.millis(), 0);
+    assert_eq!(res[0].2, 1);
+
+    let input = "@RELATION Data
+@ATTRIBUTE a NUMERIC
+@ATTRIBUTE b NUMERIC
+@DATA
+{1 2, 0 1}";
+
+    assert_eq!(
+        from_str::<Vec<(u8, u8)>>(input),
+        Err(Error::Expected(
+            TextPos::new(5, 7),
+            "sparse column indices in ascending order"
+        ))
+    );
+}
+
+#[test]
+fn test_weights() {
+    use weighted::Weighted;
+
+    let input = "@RELATION Data
+
+@ATTRIBUTE a NUMERIC
+@ATTRIBUTE b NUMERIC
+
+@DATA
+1, 2, {0.75}
+3, 4
+{1 5}, {2}
+{0 6} ,{ 0.5 } % comment";
+
+    let res: Vec<Weighted<[u8; 2]>> = from_str(input).unwrap();
+    assert_eq!(
+        res,
+        vec![
+            Weighted::new([1, 2], 0.75),
+            Weighted::new([3, 4], 1.0),
+            Weighted::new([0, 5], 2.0),
+            Weighted::new([6, 0], 0.5),
+        ]
+    );
+
+    let res: Vec<Vec<u8>> = from_str(input).unwrap();
+    assert_eq!(res, vec![vec![1, 2], vec![3, 4], vec![0, 5], vec![6, 0]]);
+
+    let input = "@RELATION Data
+@ATTRIBUTE a NUMERIC
+@DATA
+1, 2";
+
+    assert_eq!(
+        from_str::<Vec<Weighted<[u8; 1]>>>(input),
+        Err(Error::Expected(TextPos::new(4, 4), "instance weight `{`"))
+    );
+}
+
+#[test]
+fn test_relational() {
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Instance {
+        f1: i32,
+        f2: f64,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Row {
+        id: String,
+        bag: Vec<Instance>,
+        class: u8,
+    }
+
+    let input = "@RELATION musk
+
+@ATTRIBUTE id {a, b}
+@ATTRIBUTE bag RELATIONAL
+  @ATTRIBUTE f1 NUMERIC
+  @ATTRIBUTE f2 NUMERIC
+@END bag
+@ATTRIBUTE class {0, 1}
+
+@DATA
+a, '42,-1.5\\n7,0.25', 1
+b, '', 0
+{0 b, 1 '3,4'}";
+
+    let res: Vec<Row> = from_str(input).unwrap();
+    assert_eq!(
+        res,
+        vec![
+            Row {
+                id: "a".to_owned(),
+                bag: vec![Instance { f1: 42, f2: -1.5 }, Instance { f1: 7, f2: 0.25 }],
+                class: 1,
+            },
+            Row {
+                id: "b".to_owned(),
+                bag: vec![],
+                class: 0,
+            },
+            Row {
+                id: "b".to_owned(),
+                bag: vec![Instance { f1: 3, f2: 4.0 }],
+                class: 0,
+            },
+        ]
+    );
+
+    type Bag = Vec<(u8, u8)>;
+    let res: Vec<(String, Option<Bag>, u8)> =
+        from_str("@RELATION x\n@ATTRIBUTE id STRING\n@ATTRIBUTE bag RELATIONAL\n@ATTRIBUTE a NUMERIC\n@ATTRIBUTE b NUMERIC\n@END bag\n@ATTRIBUTE c NUMERIC\n@DATA\nx, ?, 1\n").unwrap();
+    assert_eq!(res, vec![("x".to_owned(), None, 1)]);
+}
+
+#[test]
+fn test_from_reader() {
+    use std::io::{self, BufReader, Read};
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Row {
+        name: String,
+        x: f64,
+    }
+
+    let input = "@RELATION test
+@ATTRIBUTE name STRING
+@ATTRIBUTE x NUMERIC
+% a comment
+@DATA
+'first row', 1.5
+second, -2
+";
+
+    // a tiny buffer makes values cross buffer boundaries
+    let reader = BufReader::with_capacity(3, input.as_bytes());
+    let res: Vec<Row> = from_reader(reader).unwrap();
+    assert_eq!(
+        res,
+        vec![
+            Row {
+                name: "first row".to_owned(),
+                x: 1.5,
+            },
+            Row {
+                name: "second".to_owned(),
+                x: -2.0,
+            },
+        ]
+    );
+
+    struct FailingReader<'a>(&'a [u8]);
+
+    impl<'a> Read for FailingReader<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.0.is_empty() {
+                return Err(io::Error::new(io::ErrorKind::BrokenPipe, "connection lost"));
+            }
+            let n = self.0.read(buf)?;
+            Ok(n)
+        }
+    }
+
+    let reader = BufReader::new(FailingReader(&input.as_bytes()[..80]));
+    let res: Result<Vec<Row>> = from_reader(reader);
+    assert_eq!(
+        res,
+        Err(Error::Io(
+            io::ErrorKind::BrokenPipe,
+            "connection lost".to_owned()
+        ))
+    );
+}
+
+#[test]
+fn test_rows() {
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Row {
+        a: u8,
+        b: Option<f64>,
+    }
+
+    let input = "@RELATION test
+@ATTRIBUTE a NUMERIC
+@ATTRIBUTE b NUMERIC
+@DATA
+1, 2.5
+
+% comment
+2, ?
+300, 1
+4, 0.5";
+
+    let mut iter = rows::<Row>(input).unwrap();
+    assert_eq!(iter.next(), Some(Ok(Row { a: 1, b: Some(2.5) })));
+    assert_eq!(iter.next(), Some(Ok(Row { a: 2, b: None })));
+    assert_eq!(
+        iter.next(),
+        Some(Err(Error::Row {
+            line: 9,
+            error: Box::new(Error::Attribute {
+                name: "a".to_owned(),
+                pos: Some(TextPos::new(9, 1)),
+                error: Box::new(Error::NumericRange(TextPos::new(9, 1), 0, 255)),
+            }),
+        }))
+    );
+    assert_eq!(iter.next(), Some(Ok(Row { a: 4, b: Some(0.5) })));
+    assert_eq!(iter.next(), None);
+    assert_eq!(iter.next(), None);
+
+    let total: u32 = rows::<Row>(input)
+        .unwrap()
+        .filter_map(Result::ok)
+        .take(2)
+        .map(|row| u32::from(row.a))
+        .sum();
+    assert_eq!(total, 3);
+
+    let err = rows::<Row>(input).unwrap().nth(2).unwrap().unwrap_err();
+    assert_eq!(
+        format!("{}", err),
+        "line 9, column 1 (attribute 'a'): value outside numeric range [0, 255]"
+    );
+}
+
+#[test]
+fn test_error_display() {
+    use std::error::Error as StdError;
+
+    let input = "@RELATION iris
+@ATTRIBUTE sepal_width NUMERIC
+@ATTRIBUTE petal_width NUMERIC
+@DATA
+3.5, 0.2
+3.0,  abc
+";
+
+    let err = from_str::<Vec<(f64, f64)>>(input).unwrap_err();
+    assert_eq!(err.pos(), Some(TextPos::new(6, 7)));
+    assert_eq!(err.attribute(), Some("petal_width"));
+    assert_eq!(
+        err.to_string(),
+        "line 6, column 7 (attribute 'petal_width'): expected float, found 'abc'"
+    );
+    assert_eq!(
+        err.display_with_source(input),
+        "line 6, column 7 (attribute 'petal_width'): expected float, found 'abc'\n\
+         3.0,  abc\n      ^"
+    );
+    assert_eq!(
+        err.source().map(|e| e.to_string()),
+        Some("line 6, column 7: expected float, found 'abc'".to_owned())
+    );
+
+    let err =
+        from_str::<Vec<[f64; 1]>>("@RELATION iris\n@ATTRIBUTE sepal_width NUMERIC\n@DATA\n?\n")
+            .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "line 4, column 1 (attribute 'sepal_width'): unexpected missing value"
+    );
+}
+
+#[test]
+fn test_lenient() {
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Row {
+        a: u8,
+        b: f64,
+    }
+
+    let input = "@RELATION test
+@ATTRIBUTE a NUMERIC
+@ATTRIBUTE b NUMERIC
+@DATA
+1, 2.5
+x, 1
+3
+4, 0.5
+";
+
+    let (data, diagnostics) = from_str_lenient::<Row>(input).unwrap();
+    assert_eq!(data, vec![Row { a: 1, b: 2.5 }, Row { a: 4, b: 0.5 }]);
+
+    assert_eq!(diagnostics.len(), 2);
+    assert_eq!(diagnostics[0].line, 6);
+    assert_eq!(diagnostics[0].attribute, Some("a".to_owned()));
+    assert_eq!(
+        diagnostics[0].to_string(),
+        "line 6, column 1 (attribute 'a'): expected unsigned integer, found 'x'"
+    );
+    assert_eq!(diagnostics[1].line, 7);
+    assert_eq!(
+        diagnostics[1].error,
+        Error::Expected(TextPos::new(7, 2), "`,` or `\t`")
+    );
+
+    let reader = ::std::io::BufReader::with_capacity(4, input.as_bytes());
+    let (data2, diagnostics2) = from_reader_lenient::<_, Row>(reader).unwrap();
+    assert_eq!(data2, data);
+    assert_eq!(diagnostics2, diagnostics);
+}
+
+#[test]
+fn test_strict() {
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Row {
+        n: f64,
+        x: f64,
+    }
+
+    let input = "@RELATION test
+@ATTRIBUTE n INTEGER
+@ATTRIBUTE x REAL
+@DATA
+1, 2.5
+1.5, 3
+";
+
+    let data: Vec<Row> = from_str(input).unwrap();
+    assert_eq!(data, vec![Row { n: 1.0, x: 2.5 }, Row { n: 1.5, x: 3.0 }]);
+
+    let err = from_str_strict::<Vec<Row>>(input).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "line 6, column 1 (attribute 'n'): expected integer, found '1.5'"
+    );
+
+    let data: Vec<Row> = from_str_strict(&input.replace("1.5,", "2.0,")).unwrap();
+    assert_eq!(data[1], Row { n: 2.0, x: 3.0 });
+}
+
+#[test]
+fn test_borrowed_str() {
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Row<'a> {
+        name: &'a str,
+        #[serde(borrow)]
+        note: Cow<'a, str>,
+    }
+
+    let input = "@RELATION people
+@ATTRIBUTE name STRING
+@ATTRIBUTE note STRING
+@DATA
+alice, 'likes cats'
+'Bob Smith', 'it\\'s complicated'
+";
+
+    let rows: Vec<Row> = from_str(input).unwrap();
+    assert_eq!(rows[0].name, "alice");
+    assert_eq!(rows[1].name, "Bob Smith");
+
+    match rows[1].note {
+        Cow::Owned(ref note) => assert_eq!(note, "it's complicated"),
+        Cow::Borrowed(_) => panic!("escaped strings cannot be borrowed"),
+    }
+    match rows[0].note {
+        Cow::Borrowed(note) => assert_eq!(note, "likes cats"),
+        Cow::Owned(_) => panic!("unescaped strings should be borrowed"),
+    }
+
+    // strings that need unescaping can't be deserialized as `&str`
+    let err = from_str::<Vec<(&str, &str)>>(input).unwrap_err();
+    assert_eq!(err.attribute(), Some("note"));
+}
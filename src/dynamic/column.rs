@@ -1,8 +1,10 @@
 use std;
 
+use date::DateFormat;
 use error::{Error, Result};
 use parser::{self, DType, DynamicValue, Parser};
 
+use super::dataset::DataSet;
 use super::value::Value;
 
 /// A dynamically typed column of an ARFF data set
@@ -10,6 +12,8 @@ use super::value::Value;
 pub struct Column {
     name: String,
     data: ColumnData,
+    /// the declared type of a numeric column: `Numeric`, `Integer` or `Real`
+    numeric: DType,
 }
 
 /// The type of a column
@@ -25,7 +29,9 @@ pub enum ColumnType {
     I64,
     F64,
     String,
+    Date { format: DateFormat },
     Nominal { categories: Vec<String> },
+    Relational { template: DataSet },
 }
 
 /// The dynamically typed data of a column
@@ -73,17 +79,53 @@ pub enum ColumnData {
         values: Vec<Option<String>>,
     },
 
+    /// milliseconds since the Unix epoch
+    Date {
+        format: DateFormat,
+        values: Vec<Option<i64>>,
+    },
+
     Nominal {
         categories: Vec<String>,
         values: Vec<Option<usize>>,
     },
+
+    /// `template` is an empty data set with the nested attributes
+    Relational {
+        template: DataSet,
+        values: Vec<Option<DataSet>>,
+    },
 }
 
 impl Column {
+    /// Create a column. Numeric columns are declared as `NUMERIC` in the schema.
     pub fn new(name: &str, data: ColumnData) -> Self {
         Column {
             name: name.to_owned(),
             data,
+            numeric: DType::Numeric,
+        }
+    }
+
+    /// Create a numeric column that is declared as `INTEGER` in the schema, like columns read
+    /// from an `INTEGER` attribute.
+    ///
+    /// The declaration only affects numeric data; it is ignored for other columns.
+    pub fn new_integer(name: &str, data: ColumnData) -> Self {
+        Column {
+            numeric: DType::Integer,
+            ..Column::new(name, data)
+        }
+    }
+
+    /// Create a numeric column that is declared as `REAL` in the schema, like columns read
+    /// from a `REAL` attribute.
+    ///
+    /// The declaration only affects numeric data; it is ignored for other columns.
+    pub fn new_real(name: &str, data: ColumnData) -> Self {
+        Column {
+            numeric: DType::Real,
+            ..Column::new(name, data)
         }
     }
 
@@ -100,13 +142,51 @@ impl Column {
     }
 
     pub(crate) fn from_attr(attr: parser::Attribute) -> Result<Self> {
+        let numeric = match attr.dtype {
+            DType::Integer => DType::Integer,
+            DType::Real => DType::Real,
+            _ => DType::Numeric,
+        };
+        let data = ColumnData::new_from_dtype(&attr.name, attr.dtype)?;
         Ok(Column {
             name: attr.name,
-            data: ColumnData::new_from_dtype(attr.dtype),
+            data,
+            numeric,
         })
     }
 
+    /// the attribute declaration that describes this column
+    pub(crate) fn to_attr(&self) -> parser::Attribute {
+        let dtype = match self.data.get_type() {
+            ColumnType::String => DType::String,
+            ColumnType::Date { format } => DType::Date(format),
+            ColumnType::Nominal { categories } => DType::Nominal(categories),
+            ColumnType::Relational { template } => DType::Relational(template.schema().attrs),
+            // an INTEGER column that had to store fractional values is not INTEGER anymore
+            ColumnType::F64 if self.numeric == DType::Integer => DType::Numeric,
+            _ => self.numeric.clone(),
+        };
+        parser::Attribute {
+            name: self.name.clone(),
+            dtype,
+        }
+    }
+
+    /// parse the next value of this column, reporting errors with the column name
     pub(crate) fn parse_value(&mut self, parser: &mut Parser) -> Result<()> {
+        let pos = parser.pos();
+        match self.parse_item(parser) {
+            Err(e @ Error::Io(_, _)) => Err(e),
+            Err(e) => Err(Error::Attribute {
+                name: self.name.clone(),
+                pos: Some(pos),
+                error: Box::new(e),
+            }),
+            Ok(()) => Ok(()),
+        }
+    }
+
+    fn parse_item(&mut self, parser: &mut Parser) -> Result<()> {
         match self.data {
             ColumnData::String {ref mut values} => {
                 if parser.parse_is_missing() {
@@ -120,7 +200,7 @@ impl Column {
                 if parser.parse_is_missing() {
                     values.push(None);
                 } else {
-                    let value = parser.parse_unquoted_string()?;
+                    let value = parser.parse_string()?;
                     match categories
                         .iter()
                         .position(|item| item == &value)
@@ -130,12 +210,123 @@ impl Column {
                         }
                 }
             }
-            _ => self.push(parser.parse_dynamic()?)
-            //ColumnData::Date {..} => unimplemented!(),
+            ColumnData::Date {ref mut values, ref format} => {
+                let pos = parser.pos();
+                if parser.parse_is_missing() {
+                    values.push(None);
+                } else {
+                    let value = parser.parse_string()?;
+                    match format.parse(&value) {
+                        Some(millis) => values.push(Some(millis)),
+                        None => return Err(Error::InvalidDate(pos, value)),
+                    }
+                }
+            }
+            ColumnData::Relational {ref mut values, ref template} => {
+                if parser.parse_is_missing() {
+                    values.push(None);
+                } else {
+                    let mut bag = template.clone();
+                    bag.parse_rows(&mut parser.parse_nested_rows()?)?;
+                    values.push(Some(bag));
+                }
+            }
+            _ => {
+                let pos = parser.pos();
+                match parser.parse_dynamic()? {
+                    Some(DynamicValue::String(value)) => {
+                        return Err(Error::ExpectedFloatValue(pos, value))
+                    }
+                    // keep INTEGER columns integer if possible
+                    Some(DynamicValue::F64(v)) if self.numeric == DType::Integer => {
+                        let integer = if v.fract() == 0.0 && v.abs() < u64::MAX as f64 {
+                            parser::integer_value(v < 0.0, v.abs() as u64)
+                        } else {
+                            None
+                        };
+                        match integer {
+                            Some(value) => self.push(Some(value)),
+                            None if parser.is_strict() => {
+                                return Err(Error::ExpectedIntegerValue(pos, v.to_string()))
+                            }
+                            None => self.push(Some(DynamicValue::F64(v))),
+                        }
+                    }
+                    value => self.push(value),
+                }
+            }
         }
         Ok(())
     }
 
+    /// push the value implied for entries omitted from a sparse row
+    pub(crate) fn push_default(&mut self) {
+        match self.data {
+            ColumnData::String { ref mut values } => values.push(Some(String::new())),
+            ColumnData::Nominal { ref mut values, .. } => values.push(Some(0)),
+            ColumnData::Date { ref mut values, .. } => values.push(Some(0)),
+            ColumnData::Relational {
+                ref mut values,
+                ref template,
+            } => values.push(Some(template.clone())),
+            _ => self.push(Some(DynamicValue::U8(0))),
+        }
+    }
+
+    /// push a missing value, e.g. for a data row that could not be parsed
+    pub(crate) fn push_missing(&mut self) {
+        match self.data {
+            ColumnData::String { ref mut values } => values.push(None),
+            ColumnData::Nominal { ref mut values, .. } => values.push(None),
+            ColumnData::Date { ref mut values, .. } => values.push(None),
+            ColumnData::Relational { ref mut values, .. } => values.push(None),
+            _ => self.push(None),
+        }
+    }
+
+    /// shorten the column to `len` values
+    pub(crate) fn truncate(&mut self, len: usize) {
+        match self.data {
+            ColumnData::U8 { ref mut values } => values.truncate(len),
+            ColumnData::U16 { ref mut values } => values.truncate(len),
+            ColumnData::U32 { ref mut values } => values.truncate(len),
+            ColumnData::U64 { ref mut values } => values.truncate(len),
+            ColumnData::I8 { ref mut values } => values.truncate(len),
+            ColumnData::I16 { ref mut values } => values.truncate(len),
+            ColumnData::I32 { ref mut values } => values.truncate(len),
+            ColumnData::I64 { ref mut values } => values.truncate(len),
+            ColumnData::F64 { ref mut values } => values.truncate(len),
+            ColumnData::String { ref mut values } => values.truncate(len),
+            ColumnData::Date { ref mut values, .. } => values.truncate(len),
+            ColumnData::Nominal { ref mut values, .. } => values.truncate(len),
+            ColumnData::Relational { ref mut values, .. } => values.truncate(len),
+            ColumnData::Invalid => panic!("invalid column state"),
+        }
+    }
+
+    /// remove all values, and return numeric data to the type of a column without values
+    pub(crate) fn clear(&mut self) {
+        self.truncate(0);
+        if self.data.is_numeric() {
+            let data = match self.numeric {
+                DType::Real => ColumnData::new_real(),
+                _ => ColumnData::new_numeric(),
+            };
+            if data.get_type() != self.data.get_type() {
+                self.data = data;
+            }
+        }
+    }
+
+    /// move the values of a column with the same attribute to the end of this one, e.g. parsed
+    /// from another part of the input
+    ///
+    /// Numeric columns are promoted to a type that can hold the values of both. `other` is left
+    /// without values, but possibly with a promoted type.
+    pub(crate) fn append(&mut self, other: &mut Column) {
+        self.data.append(&mut other.data);
+    }
+
     fn push(&mut self, value: Option<DynamicValue>) {
         let data = std::mem::replace(&mut self.data, ColumnData::Invalid);
 
@@ -152,7 +343,8 @@ impl Column {
                 self.data = data.into_u64().pushed_u64(Some(v))
             }
             (ColumnType::U8, Some(DynamicValue::I8(v))) => {
-                if data.is_empty() {
+                // missing values alone do not make the column unsigned
+                if !data.has_values() {
                     self.data = data.into_i8().pushed_i8(Some(v))
                 } else {
                     self.data = data.into_i16().pushed_i16(Some(v as i16))
@@ -388,7 +580,9 @@ impl Column {
             (ColumnType::F64, Some(DynamicValue::F64(v))) => self.data = data.pushed_f64(Some(v)),
 
             (ColumnType::String, _) => unreachable!(),
+            (ColumnType::Date { .. }, _) => unreachable!(),
             (ColumnType::Nominal { .. }, _) => unreachable!(),
+            (ColumnType::Relational { .. }, _) => unreachable!(),
             (_, Some(DynamicValue::String(_))) => unimplemented!(),
         }
     }
@@ -406,6 +600,7 @@ impl Column {
             ColumnData::I64 { ref values } => values[idx].into(),
             ColumnData::F64 { ref values } => values[idx].into(),
             ColumnData::String { ref values } => values[idx].as_ref().map(|x| x.as_str()).into(),
+            ColumnData::Date { ref values, .. } => values[idx].map_or(Value::Missing, Value::Date),
             ColumnData::Nominal {
                 ref categories,
                 ref values,
@@ -413,6 +608,10 @@ impl Column {
                 Some(v) => Value::Nominal(v, &categories),
                 None => Value::Missing,
             },
+            ColumnData::Relational { ref values, .. } => match values[idx] {
+                Some(ref bag) => Value::Relational(bag),
+                None => Value::Missing,
+            },
             ColumnData::Invalid => panic!("invalid column state"),
         }
     }
@@ -443,8 +642,11 @@ macro_rules! def_columndata_into {
                 ColumnData::String{values} => values.into_iter()
                                                     .map(|x| x.map(|v| v.parse().unwrap()))
                                                     .collect(),
+                ColumnData::Date{values, ..} => values.into_iter()
+                                                      .map(|x| x.map(|v| v as $typ)).collect(),
                 ColumnData::Nominal{values, ..} => values.into_iter()
                                                          .map(|x| x.map(|v| v as $typ)).collect(),
+                ColumnData::Relational{..} => panic!("relational column can't be converted"),
                 ColumnData::Invalid => panic!("invalid column state"),
             };
             ColumnData::$variant{values}
@@ -466,22 +668,38 @@ macro_rules! def_columndata_pushed {
 }
 
 impl ColumnData {
-    fn new_from_dtype(dt: DType) -> Self {
-        match dt {
-            DType::Numeric => ColumnData::new_numeric(),
+    fn new_from_dtype(name: &str, dt: DType) -> Result<Self> {
+        Ok(match dt {
+            DType::Numeric | DType::Integer => ColumnData::new_numeric(),
+            DType::Real => ColumnData::new_real(),
             DType::String => ColumnData::new_string(),
+            DType::Date(format) => ColumnData::new_date(format),
             DType::Nominal(names) => ColumnData::new_nominal(names),
-        }
+            DType::Relational(attrs) => {
+                ColumnData::new_relational(DataSet::from_attributes(name.to_owned(), attrs)?)
+            }
+        })
     }
 
     fn new_numeric() -> Self {
         ColumnData::U8 { values: Vec::new() }
     }
 
+    fn new_real() -> Self {
+        ColumnData::F64 { values: Vec::new() }
+    }
+
     fn new_string() -> Self {
         ColumnData::String { values: Vec::new() }
     }
 
+    fn new_date(format: DateFormat) -> Self {
+        ColumnData::Date {
+            format,
+            values: Vec::new(),
+        }
+    }
+
     fn new_nominal(categories: Vec<String>) -> Self {
         ColumnData::Nominal {
             categories,
@@ -489,6 +707,49 @@ impl ColumnData {
         }
     }
 
+    fn new_relational(template: DataSet) -> Self {
+        ColumnData::Relational {
+            template,
+            values: Vec::new(),
+        }
+    }
+
+    /// is the data of a numeric type, which may be promoted?
+    fn is_numeric(&self) -> bool {
+        matches!(
+            *self,
+            ColumnData::U8 { .. }
+                | ColumnData::U16 { .. }
+                | ColumnData::U32 { .. }
+                | ColumnData::U64 { .. }
+                | ColumnData::I8 { .. }
+                | ColumnData::I16 { .. }
+                | ColumnData::I32 { .. }
+                | ColumnData::I64 { .. }
+                | ColumnData::F64 { .. }
+        )
+    }
+
+    /// does the column contain any value that is not missing?
+    fn has_values(&self) -> bool {
+        match *self {
+            ColumnData::U8 { ref values } => values.iter().any(Option::is_some),
+            ColumnData::U16 { ref values } => values.iter().any(Option::is_some),
+            ColumnData::U32 { ref values } => values.iter().any(Option::is_some),
+            ColumnData::U64 { ref values } => values.iter().any(Option::is_some),
+            ColumnData::I8 { ref values } => values.iter().any(Option::is_some),
+            ColumnData::I16 { ref values } => values.iter().any(Option::is_some),
+            ColumnData::I32 { ref values } => values.iter().any(Option::is_some),
+            ColumnData::I64 { ref values } => values.iter().any(Option::is_some),
+            ColumnData::F64 { ref values } => values.iter().any(Option::is_some),
+            ColumnData::String { ref values } => values.iter().any(Option::is_some),
+            ColumnData::Date { ref values, .. } => values.iter().any(Option::is_some),
+            ColumnData::Nominal { ref values, .. } => values.iter().any(Option::is_some),
+            ColumnData::Relational { ref values, .. } => values.iter().any(Option::is_some),
+            ColumnData::Invalid => panic!("invalid column state"),
+        }
+    }
+
     pub fn len(&self) -> usize {
         match *self {
             ColumnData::U8 { ref values } => values.len(),
@@ -501,7 +762,9 @@ impl ColumnData {
             ColumnData::I64 { ref values } => values.len(),
             ColumnData::F64 { ref values } => values.len(),
             ColumnData::String { ref values } => values.len(),
+            ColumnData::Date { ref values, .. } => values.len(),
             ColumnData::Nominal { ref values, .. } => values.len(),
+            ColumnData::Relational { ref values, .. } => values.len(),
             ColumnData::Invalid => panic!("invalid column state"),
         }
     }
@@ -518,26 +781,15 @@ impl ColumnData {
             ColumnData::I64 { .. } => ColumnType::I64,
             ColumnData::F64 { .. } => ColumnType::F64,
             ColumnData::String { .. } => ColumnType::String,
+            ColumnData::Date { ref format, .. } => ColumnType::Date {
+                format: format.clone(),
+            },
             ColumnData::Nominal { ref categories, .. } => ColumnType::Nominal {
                 categories: categories.clone(),
             },
-            ColumnData::Invalid => panic!("invalid column state"),
-        }
-    }
-
-    fn is_empty(&self) -> bool {
-        match *self {
-            ColumnData::U8 { ref values } => values.is_empty(),
-            ColumnData::U16 { ref values } => values.is_empty(),
-            ColumnData::U32 { ref values } => values.is_empty(),
-            ColumnData::U64 { ref values } => values.is_empty(),
-            ColumnData::I8 { ref values } => values.is_empty(),
-            ColumnData::I16 { ref values } => values.is_empty(),
-            ColumnData::I32 { ref values } => values.is_empty(),
-            ColumnData::I64 { ref values } => values.is_empty(),
-            ColumnData::F64 { ref values } => values.is_empty(),
-            ColumnData::String { ref values } => values.is_empty(),
-            ColumnData::Nominal { ref values, .. } => values.is_empty(),
+            ColumnData::Relational { ref template, .. } => ColumnType::Relational {
+                template: template.clone(),
+            },
             ColumnData::Invalid => panic!("invalid column state"),
         }
     }
@@ -561,3 +813,186 @@ impl ColumnData {
     def_columndata_into!(into_i64, I64, i64);
     def_columndata_into!(into_f64, F64, f64);
 }
+
+impl ColumnData {
+    /// move the values of `other` to the end of `self`
+    ///
+    /// Numeric data is promoted like `Column::push` would when pushing the values of `other` one
+    /// by one. Missing values alone do not cause a promotion.
+    fn append(&mut self, other: &mut ColumnData) {
+        // other data has the same type in both columns, and comparing the types would copy
+        // nominal categories and relational templates
+        if self.is_numeric() {
+            let (self_type, other_type) = (self.get_type(), other.get_type());
+            let typ = if self_type == other_type || !other.has_values() {
+                self_type
+            } else if !self.has_values() {
+                other_type
+            } else {
+                promoted(&self_type, &other_type)
+            };
+            self.convert(&typ);
+            other.convert(&typ);
+        }
+        self.extend(other);
+    }
+
+    /// convert numeric data to the given type
+    fn convert(&mut self, typ: &ColumnType) {
+        if self.get_type() == *typ {
+            return;
+        }
+        let data = std::mem::replace(self, ColumnData::Invalid);
+        *self = match *typ {
+            ColumnType::U16 => data.into_u16(),
+            ColumnType::U32 => data.into_u32(),
+            ColumnType::U64 => data.into_u64(),
+            ColumnType::I8 => data.into_i8(),
+            ColumnType::I16 => data.into_i16(),
+            ColumnType::I32 => data.into_i32(),
+            ColumnType::I64 => data.into_i64(),
+            ColumnType::F64 => data.into_f64(),
+            _ => panic!("unexpected type: {:?} (expected {:?})", data, typ),
+        };
+    }
+
+    /// move the values of data of the same type
+    fn extend(&mut self, other: &mut ColumnData) {
+        match (self, other) {
+            (
+                &mut ColumnData::U8 { ref mut values },
+                &mut ColumnData::U8 {
+                    values: ref mut more,
+                },
+            ) => values.append(more),
+            (
+                &mut ColumnData::U16 { ref mut values },
+                &mut ColumnData::U16 {
+                    values: ref mut more,
+                },
+            ) => values.append(more),
+            (
+                &mut ColumnData::U32 { ref mut values },
+                &mut ColumnData::U32 {
+                    values: ref mut more,
+                },
+            ) => values.append(more),
+            (
+                &mut ColumnData::U64 { ref mut values },
+                &mut ColumnData::U64 {
+                    values: ref mut more,
+                },
+            ) => values.append(more),
+            (
+                &mut ColumnData::I8 { ref mut values },
+                &mut ColumnData::I8 {
+                    values: ref mut more,
+                },
+            ) => values.append(more),
+            (
+                &mut ColumnData::I16 { ref mut values },
+                &mut ColumnData::I16 {
+                    values: ref mut more,
+                },
+            ) => values.append(more),
+            (
+                &mut ColumnData::I32 { ref mut values },
+                &mut ColumnData::I32 {
+                    values: ref mut more,
+                },
+            ) => values.append(more),
+            (
+                &mut ColumnData::I64 { ref mut values },
+                &mut ColumnData::I64 {
+                    values: ref mut more,
+                },
+            ) => values.append(more),
+            (
+                &mut ColumnData::F64 { ref mut values },
+                &mut ColumnData::F64 {
+                    values: ref mut more,
+                },
+            ) => values.append(more),
+            (
+                &mut ColumnData::String { ref mut values },
+                &mut ColumnData::String {
+                    values: ref mut more,
+                },
+            ) => values.append(more),
+            (
+                &mut ColumnData::Date { ref mut values, .. },
+                &mut ColumnData::Date {
+                    values: ref mut more,
+                    ..
+                },
+            ) => values.append(more),
+            (
+                &mut ColumnData::Nominal { ref mut values, .. },
+                &mut ColumnData::Nominal {
+                    values: ref mut more,
+                    ..
+                },
+            ) => values.append(more),
+            (
+                &mut ColumnData::Relational { ref mut values, .. },
+                &mut ColumnData::Relational {
+                    values: ref mut more,
+                    ..
+                },
+            ) => values.append(more),
+            (data, other) => panic!(
+                "unexpected type: {:?} (expected {:?})",
+                other,
+                data.get_type()
+            ),
+        }
+    }
+}
+
+/// the smallest numeric type that can hold the values of both types
+///
+/// Unsigned and signed integers are combined into a signed type of twice the unsigned width, as
+/// in `Column::push`.
+fn promoted(a: &ColumnType, b: &ColumnType) -> ColumnType {
+    match (integer_width(a), integer_width(b)) {
+        (Some((signed_a, bits_a)), Some((signed_b, bits_b))) if signed_a == signed_b => {
+            integer_type(signed_a, std::cmp::max(bits_a, bits_b))
+        }
+        (Some((false, unsigned)), Some((true, signed)))
+        | (Some((true, signed)), Some((false, unsigned)))
+            if unsigned < 64 =>
+        {
+            integer_type(true, std::cmp::max(signed, 2 * unsigned))
+        }
+        _ => ColumnType::F64,
+    }
+}
+
+/// signedness and number of bits of an integer column type
+fn integer_width(typ: &ColumnType) -> Option<(bool, u32)> {
+    match *typ {
+        ColumnType::U8 => Some((false, 8)),
+        ColumnType::U16 => Some((false, 16)),
+        ColumnType::U32 => Some((false, 32)),
+        ColumnType::U64 => Some((false, 64)),
+        ColumnType::I8 => Some((true, 8)),
+        ColumnType::I16 => Some((true, 16)),
+        ColumnType::I32 => Some((true, 32)),
+        ColumnType::I64 => Some((true, 64)),
+        _ => None,
+    }
+}
+
+fn integer_type(signed: bool, bits: u32) -> ColumnType {
+    match (signed, bits) {
+        (false, 8) => ColumnType::U8,
+        (false, 16) => ColumnType::U16,
+        (false, 32) => ColumnType::U32,
+        (false, 64) => ColumnType::U64,
+        (true, 8) => ColumnType::I8,
+        (true, 16) => ColumnType::I16,
+        (true, 32) => ColumnType::I32,
+        (true, 64) => ColumnType::I64,
+        _ => unreachable!(),
+    }
+}
@@ -1,18 +1,39 @@
 use std::collections::HashSet;
-
-use error::Result;
-use parser::Parser;
+use std::io::BufRead;
+use std::path::Path;
+#[cfg(feature = "mmap")]
+use std::str;
+
+use error::{Diagnostic, Error, Result};
+#[cfg(feature = "mmap")]
+use file::map_file;
+use file::open_file;
+use parser::{Attribute, Header, Parser};
+use weighted::DEFAULT_WEIGHT;
 
 use super::column::Column;
 use super::value::Value;
 use super::FlatIter;
 
+/// How data rows that fail to parse are handled in lenient mode
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum BadRows {
+    /// leave the row out of the data set
+    Skip,
+    /// keep the row, with all of its values missing
+    Missing,
+}
+
 /// A dynamically typed representation of an ARFF data set
 #[derive(Debug, Clone, PartialEq)]
 pub struct DataSet {
     relation: String,
     columns: Vec<Column>,
+    weights: Vec<f64>,
     n_rows: usize,
+    relation_comments: Vec<String>,
+    comments: Vec<(usize, String)>,
+    data_comments: Vec<(usize, String)>,
 }
 
 impl DataSet {
@@ -27,7 +48,11 @@ impl DataSet {
         DataSet {
             relation: relation.to_owned(),
             columns,
+            weights: vec![DEFAULT_WEIGHT; n_rows],
             n_rows,
+            relation_comments: Vec::new(),
+            comments: Vec::new(),
+            data_comments: Vec::new(),
         }
     }
 
@@ -35,45 +60,320 @@ impl DataSet {
         &self.relation
     }
 
+    /// The full-line comments before the relation declaration
+    pub fn relation_comments(&self) -> &[String] {
+        &self.relation_comments
+    }
+
+    /// Replace the comments before the relation declaration, e.g. to note where the data comes
+    /// from
+    pub fn set_relation_comments(&mut self, comments: Vec<String>) {
+        self.relation_comments = comments;
+    }
+
+    /// The full-line comments of the header after the relation declaration, each with the index
+    /// of the column that follows it
+    pub fn comments(&self) -> &[(usize, String)] {
+        &self.comments
+    }
+
+    /// Replace the comments of the header, e.g. to note how the data set was derived
+    pub fn set_comments(&mut self, comments: Vec<(usize, String)>) {
+        self.comments = comments;
+    }
+
+    /// The full-line comments between the data rows, each with the index of the row that follows
+    /// it
+    pub fn data_comments(&self) -> &[(usize, String)] {
+        &self.data_comments
+    }
+
     /// Deserialize an instance of type `DataSet` from an ARFF formatted string.
     pub fn from_str(input: &str) -> Result<Self> {
+        DataSet::from_parser(&mut Parser::new(input))
+    }
+
+    /// Deserialize an instance of type `DataSet` from a reader of ARFF formatted text.
+    ///
+    /// The input is parsed incrementally, without loading it into memory as a whole.
+    pub fn from_reader<R: BufRead>(reader: R) -> Result<Self> {
+        let mut parser = Parser::from_reader(reader);
+        let result = DataSet::from_parser(&mut parser);
+        parser.check_io(result)
+    }
+
+    /// Deserialize an instance of type `DataSet` from an ARFF formatted string, checking the
+    /// values against the declared attribute types.
+    ///
+    /// In particular, values of `INTEGER` attributes must be whole numbers. Otherwise, such
+    /// columns are stored as floating point numbers.
+    pub fn from_str_strict(input: &str) -> Result<Self> {
         let mut parser = Parser::new(input);
+        parser.set_strict(true);
+        DataSet::from_parser(&mut parser)
+    }
+
+    /// Deserialize an instance of type `DataSet` from a reader of ARFF formatted text, checking
+    /// the values against the declared attribute types like `from_str_strict`.
+    pub fn from_reader_strict<R: BufRead>(reader: R) -> Result<Self> {
+        let mut parser = Parser::from_reader(reader);
+        parser.set_strict(true);
+        let result = DataSet::from_parser(&mut parser);
+        parser.check_io(result)
+    }
+
+    /// Deserialize an instance of type `DataSet` from an ARFF formatted string, without failing on
+    /// data rows that cannot be parsed.
+    ///
+    /// Such rows are skipped or filled with missing values, as chosen by `bad_rows`, and reported
+    /// in the returned diagnostics. Errors in the header still fail as a whole.
+    pub fn from_str_lenient(input: &str, bad_rows: BadRows) -> Result<(Self, Vec<Diagnostic>)> {
+        DataSet::from_parser_lenient(&mut Parser::new(input), bad_rows)
+    }
+
+    /// Deserialize an instance of type `DataSet` from a reader of ARFF formatted text, without
+    /// failing on data rows that cannot be parsed.
+    ///
+    /// Like `from_str_lenient`, but read errors fail as a whole.
+    pub fn from_reader_lenient<R: BufRead>(
+        reader: R,
+        bad_rows: BadRows,
+    ) -> Result<(Self, Vec<Diagnostic>)> {
+        let mut parser = Parser::from_reader(reader);
+        let result = DataSet::from_parser_lenient(&mut parser, bad_rows);
+        parser.check_io(result)
+    }
+
+    /// Deserialize an instance of type `DataSet` from an ARFF file, which may be gzip
+    /// compressed.
+    ///
+    /// With the `mmap` feature, uncompressed files are memory-mapped and parsed directly from
+    /// the mapping. The file must not be modified by another process while it is being parsed.
+    /// Invalid UTF-8 in the file results in an `Error::Utf8Error`.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        #[cfg(feature = "mmap")]
+        {
+            if let Some(map) = map_file(path.as_ref())? {
+                let input = str::from_utf8(&map).map_err(Error::Utf8Error)?;
+                return DataSet::from_str(input);
+            }
+        }
+        DataSet::from_reader(open_file(path)?)
+    }
+
+    fn from_parser(parser: &mut Parser) -> Result<Self> {
         let header = parser.parse_header()?;
 
+        let mut dset = DataSet::from_attributes(header.name, header.attrs)?;
+        dset.relation_comments = header.relation_comments;
+        dset.comments = header.comments;
+        dset.parse_rows(parser)?;
+        Ok(dset)
+    }
+
+    fn from_parser_lenient(
+        parser: &mut Parser,
+        bad_rows: BadRows,
+    ) -> Result<(Self, Vec<Diagnostic>)> {
+        let header = parser.parse_header()?;
+
+        let mut dset = DataSet::from_attributes(header.name, header.attrs)?;
+        dset.relation_comments = header.relation_comments;
+        dset.comments = header.comments;
+        let diagnostics = dset.parse_rows_lenient(parser, bad_rows)?;
+        Ok((dset, diagnostics))
+    }
+
+    /// The header that describes the data set: its name and the attributes of all columns
+    ///
+    /// Numeric columns keep the type they were declared with: `NUMERIC`, `INTEGER` or `REAL` when
+    /// read from a file, and the type chosen with `Column::new`, `Column::new_integer` or
+    /// `Column::new_real` otherwise. An `INTEGER` column that had to store fractional values,
+    /// which only happens without strict parsing, is declared as `NUMERIC` instead.
+    pub fn schema(&self) -> Header {
+        Header {
+            name: self.relation.clone(),
+            attrs: self.columns.iter().map(Column::to_attr).collect(),
+            relation_comments: self.relation_comments.clone(),
+            comments: self.comments.clone(),
+        }
+    }
+
+    /// create an empty data set with the given attributes
+    pub(crate) fn from_attributes(relation: String, attrs: Vec<Attribute>) -> Result<Self> {
         let mut columns = Vec::new();
 
-        for attr in header.attrs.into_iter() {
+        for attr in attrs.into_iter() {
             columns.push(Column::from_attr(attr)?);
         }
 
-        let mut n_rows = 0;
+        Ok(DataSet {
+            relation,
+            columns,
+            weights: Vec::new(),
+            n_rows: 0,
+            relation_comments: Vec::new(),
+            comments: Vec::new(),
+            data_comments: Vec::new(),
+        })
+    }
+
+    /// parse data rows until the end of input and append them to the data set
+    pub(crate) fn parse_rows(&mut self, parser: &mut Parser) -> Result<()> {
+        self.skip_empty(parser);
+        while !parser.is_eof() {
+            self.parse_row(parser)?;
+            self.skip_empty(parser);
+        }
+        Ok(())
+    }
+
+    /// move the rows of a data set with the same attributes to the end of this one, e.g. parsed
+    /// from another part of the input
+    ///
+    /// `other` is left without rows.
+    pub(crate) fn append(&mut self, other: &mut DataSet) {
+        for (col, more) in self.columns.iter_mut().zip(&mut other.columns) {
+            col.append(more);
+        }
+        let offset = self.n_rows;
+        self.data_comments.extend(
+            other
+                .data_comments
+                .drain(..)
+                .map(|(row, comment)| (offset + row, comment)),
+        );
+        self.weights.append(&mut other.weights);
+        self.n_rows += other.n_rows;
+        other.n_rows = 0;
+    }
 
-        parser.skip_empty();
+    /// remove all rows, and return numeric columns to the type of a column without values
+    fn clear(&mut self) {
+        for col in &mut self.columns {
+            col.clear();
+        }
+        self.weights.clear();
+        self.n_rows = 0;
+        self.data_comments.clear();
+    }
+
+    /// parse data rows like `parse_rows`, but resume after rows that fail to parse
+    ///
+    /// Returns a diagnostic for each of these rows. Read errors are not recoverable.
+    fn parse_rows_lenient(
+        &mut self,
+        parser: &mut Parser,
+        bad_rows: BadRows,
+    ) -> Result<Vec<Diagnostic>> {
+        // each row is parsed into a separate data set first, so that the values of a bad row do
+        // not promote the types of the good data
+        let mut row = DataSet {
+            relation: String::new(),
+            columns: self.columns.clone(),
+            weights: Vec::new(),
+            n_rows: 0,
+            relation_comments: Vec::new(),
+            comments: Vec::new(),
+            data_comments: Vec::new(),
+        };
+        row.clear();
+
+        let mut diagnostics = Vec::new();
+        self.skip_empty(parser);
         while !parser.is_eof() {
-            let mut cit = columns.iter_mut();
+            let line = parser.pos().line();
+            match row.parse_row(parser) {
+                Ok(()) => self.append(&mut row),
+                Err(e @ Error::Io(_, _)) => return Err(e),
+                Err(e) => {
+                    if bad_rows == BadRows::Missing {
+                        for col in &mut self.columns {
+                            col.push_missing();
+                        }
+                        self.weights.push(DEFAULT_WEIGHT);
+                        self.n_rows += 1;
+                    }
+                    diagnostics.push(Diagnostic::new(line, e));
+                    parser.skip_line();
+                }
+            }
+            row.clear();
+            self.skip_empty(parser);
+        }
+        Ok(diagnostics)
+    }
+
+    /// skip empty lines and comments, keeping the comments for the next row
+    fn skip_empty(&mut self, parser: &mut Parser) {
+        let mut comments = Vec::new();
+        parser.skip_empty_comments(&mut comments);
+        let row = self.n_rows;
+        self.data_comments
+            .extend(comments.into_iter().map(|comment| (row, comment)));
+    }
+
+    /// parse one data row, including its weight and the row delimiter
+    fn parse_row(&mut self, parser: &mut Parser) -> Result<()> {
+        if parser.parse_is_sparse() {
+            Self::parse_sparse_row(parser, &mut self.columns)?;
+        } else {
+            let mut cit = self.columns.iter_mut();
 
             match cit.next() {
                 None => {}
                 Some(col) => {
-                    col.parse_value(&mut parser)?;
+                    col.parse_value(parser)?;
                 }
             }
 
             for col in cit {
                 parser.parse_column_delimiter()?;
-                col.parse_value(&mut parser)?;
+                col.parse_value(parser)?;
             }
-            parser.parse_row_delimiter()?;
-            parser.skip_empty();
-
-            n_rows += 1;
         }
+        let weight = parser.parse_weight()?.unwrap_or(DEFAULT_WEIGHT);
+        parser.parse_row_delimiter()?;
 
-        Ok(DataSet {
-            relation: header.name,
-            columns,
-            n_rows,
-        })
+        self.weights.push(weight);
+        self.n_rows += 1;
+        Ok(())
+    }
+
+    /// parse the remainder of a sparse row, after the opening `{`
+    ///
+    /// Columns that are omitted from the row are filled with their default value.
+    fn parse_sparse_row(parser: &mut Parser, columns: &mut [Column]) -> Result<()> {
+        let mut next_col = 0;
+        loop {
+            let pos = parser.pos();
+            let idx = match parser.parse_sparse_index()? {
+                Some(idx) => idx,
+                None => break,
+            };
+            if idx < next_col {
+                return Err(Error::Expected(
+                    pos,
+                    "sparse column indices in ascending order",
+                ));
+            }
+            if idx >= columns.len() {
+                return Err(Error::Expected(
+                    pos,
+                    "sparse column index within the attributes",
+                ));
+            }
+            for col in &mut columns[next_col..idx] {
+                col.push_default();
+            }
+            columns[idx].parse_value(parser)?;
+            parser.parse_sparse_delimiter()?;
+            next_col = idx + 1;
+        }
+        for col in &mut columns[next_col..] {
+            col.push_default();
+        }
+        Ok(())
     }
 
     /// number of rows
@@ -81,6 +381,26 @@ impl DataSet {
         self.n_rows
     }
 
+    /// instance weights of all rows
+    ///
+    /// Rows that do not specify a weight have a weight of 1.
+    pub fn weights(&self) -> &[f64] {
+        &self.weights
+    }
+
+    /// instance weight of a row
+    pub fn weight(&self, idx: usize) -> f64 {
+        self.weights[idx]
+    }
+
+    /// replace the instance weights
+    ///
+    /// panics if the number of weights does not match the number of rows.
+    pub fn set_weights(&mut self, weights: Vec<f64>) {
+        assert_eq!(weights.len(), self.n_rows);
+        self.weights = weights;
+    }
+
     /// number of columns
     pub fn n_cols(&self) -> usize {
         self.columns.len()
@@ -141,13 +461,21 @@ impl DataSet {
         let mut a = DataSet {
             relation: self.relation.clone(),
             columns: Vec::new(),
+            weights: self.weights.clone(),
             n_rows: self.n_rows,
+            relation_comments: self.relation_comments.clone(),
+            comments: self.comments.clone(),
+            data_comments: self.data_comments.clone(),
         };
 
         let mut b = DataSet {
             relation: self.relation.clone(),
             columns: Vec::new(),
+            weights: self.weights.clone(),
             n_rows: self.n_rows,
+            relation_comments: self.relation_comments.clone(),
+            comments: self.comments.clone(),
+            data_comments: self.data_comments.clone(),
         };
 
         for col in self.columns {
@@ -166,13 +494,21 @@ impl DataSet {
         let mut a = DataSet {
             relation: self.relation.clone(),
             columns: Vec::new(),
+            weights: self.weights.clone(),
             n_rows: self.n_rows,
+            relation_comments: self.relation_comments.clone(),
+            comments: self.comments.clone(),
+            data_comments: self.data_comments.clone(),
         };
 
         let mut b = DataSet {
             relation: self.relation.clone(),
             columns: Vec::new(),
+            weights: self.weights.clone(),
             n_rows: self.n_rows,
+            relation_comments: self.relation_comments.clone(),
+            comments: self.comments.clone(),
+            data_comments: self.data_comments.clone(),
         };
 
         for col in self.columns {
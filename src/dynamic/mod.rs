@@ -1,17 +1,19 @@
 mod column;
 mod dataset;
 mod iter;
+#[cfg(feature = "parallel")]
+mod parallel;
 mod value;
 
 pub mod de;
 
 pub use self::column::Column;
-pub use self::dataset::DataSet;
+pub use self::dataset::{BadRows, DataSet};
 pub use self::iter::FlatIter;
 pub use self::value::{CastValue, Value};
 
 #[cfg(test)]
-use self::column::ColumnData;
+use self::column::{ColumnData, ColumnType};
 
 #[test]
 fn dynamic_loader() {
@@ -62,3 +64,396 @@ fn dynamic_loader() {
         )
     );
 }
+
+#[test]
+fn dynamic_dates() {
+    use date::DateFormat;
+
+    let input = "\
+@Relation 'Test data'
+@Attribute when DATE 'dd.MM.yyyy'
+@Data
+01.01.1970
+?
+02.01.1970
+";
+
+    let dset: DataSet = DataSet::from_str(input).unwrap();
+
+    assert_eq!(
+        dset,
+        DataSet::new(
+            "Test data",
+            vec![Column::new(
+                "when",
+                ColumnData::Date {
+                    format: DateFormat::new("dd.MM.yyyy").unwrap(),
+                    values: vec![Some(0), None, Some(86_400_000)],
+                },
+            )]
+        )
+    );
+    assert_eq!(dset.item(2, 0), Value::Date(86_400_000));
+}
+
+#[test]
+fn dynamic_sparse() {
+    let input = "\
+@Relation 'Test data'
+@Attribute x NUMERIC
+@Attribute y NUMERIC
+@Attribute word STRING
+@Attribute class {a, b}
+@Data
+{1 -2, 3 b}
+{}
+0.5, 1, foo, a
+{2 bar}
+";
+
+    let dset: DataSet = DataSet::from_str(input).unwrap();
+
+    assert_eq!(
+        dset,
+        DataSet::new(
+            "Test data",
+            vec![
+                Column::new(
+                    "x",
+                    ColumnData::F64 {
+                        values: vec![Some(0.0), Some(0.0), Some(0.5), Some(0.0)],
+                    },
+                ),
+                Column::new(
+                    "y",
+                    ColumnData::I16 {
+                        values: vec![Some(-2), Some(0), Some(1), Some(0)],
+                    },
+                ),
+                Column::new(
+                    "word",
+                    ColumnData::String {
+                        values: vec![
+                            Some("".to_owned()),
+                            Some("".to_owned()),
+                            Some("foo".to_owned()),
+                            Some("bar".to_owned()),
+                        ],
+                    },
+                ),
+                Column::new(
+                    "class",
+                    ColumnData::Nominal {
+                        values: vec![Some(1), Some(0), Some(0), Some(0)],
+                        categories: vec!["a".to_owned(), "b".to_owned()],
+                    },
+                ),
+            ]
+        )
+    );
+}
+
+#[test]
+fn dynamic_sparse_errors() {
+    use error::Error;
+    use parser::TextPos;
+
+    let input = "\
+@Relation 'Test data'
+@Attribute x NUMERIC
+@Attribute y NUMERIC
+@Data
+{1 1, 0 2}
+";
+    assert_eq!(
+        DataSet::from_str(input),
+        Err(Error::Expected(
+            TextPos::new(5, 7),
+            "sparse column indices in ascending order"
+        ))
+    );
+
+    let input = "\
+@Relation 'Test data'
+@Attribute x NUMERIC
+@Attribute y NUMERIC
+@Data
+{2 1}
+";
+    assert_eq!(
+        DataSet::from_str(input),
+        Err(Error::Expected(
+            TextPos::new(5, 2),
+            "sparse column index within the attributes"
+        ))
+    );
+}
+
+#[test]
+fn dynamic_weights() {
+    let input = "\
+@Relation 'Test data'
+@Attribute x NUMERIC
+@Attribute y {a, b}
+@Data
+1, a, {0.25}
+2, b
+{1 b}, {3}
+";
+
+    let dset: DataSet = DataSet::from_str(input).unwrap();
+
+    assert_eq!(dset.n_rows(), 3);
+    assert_eq!(dset.weights(), &[0.25, 1.0, 3.0]);
+    assert_eq!(dset.weight(2), 3.0);
+    assert_eq!(dset.item(2, 0), Value::U8(0));
+}
+
+#[test]
+fn dynamic_relational() {
+    let input = "\
+@Relation musk
+@Attribute id {a, b}
+@Attribute bag RELATIONAL
+@Attribute f1 NUMERIC
+@Attribute f2 {x, y}
+@End bag
+@Data
+a, '1,x\\n2,y'
+b, ?
+";
+
+    let dset: DataSet = DataSet::from_str(input).unwrap();
+
+    assert_eq!(dset.n_rows(), 2);
+    assert_eq!(dset.item(1, 1), Value::Missing);
+
+    let bag = match dset.item(0, 1) {
+        Value::Relational(bag) => bag,
+        v => panic!("expected relational value, got {:?}", v),
+    };
+    assert_eq!(bag.name(), "bag");
+    assert_eq!(bag.n_rows(), 2);
+    assert_eq!(bag.item(1, 0), Value::U8(2));
+    assert_eq!(bag.item(1, 1).as_str(), Ok("y"));
+}
+
+#[test]
+fn dynamic_quoted_nominal() {
+    let input = "\
+@Relation cities
+@Attribute city {'New York', 'Washington, D.C.', Boston}
+@Data
+'Washington, D.C.'
+Boston
+'New York'
+";
+
+    let dset: DataSet = DataSet::from_str(input).unwrap();
+
+    assert_eq!(dset.item(0, 0).as_str(), Ok("Washington, D.C."));
+    assert_eq!(dset.item(1, 0).as_str(), Ok("Boston"));
+    assert_eq!(dset.item(2, 0).as_str(), Ok("New York"));
+}
+
+#[test]
+fn dynamic_from_reader() {
+    use std::io::BufReader;
+
+    let input = "\
+@Relation data
+@Attribute x NUMERIC
+@Attribute y {a, b}
+@Data
+1, a
+2, b
+";
+
+    let reader = BufReader::with_capacity(4, input.as_bytes());
+    let dset = DataSet::from_reader(reader).unwrap();
+
+    assert_eq!(dset, DataSet::from_str(input).unwrap());
+    assert_eq!(dset.n_rows(), 2);
+    assert_eq!(dset.item(1, 1).as_str(), Ok("b"));
+}
+
+#[test]
+fn dynamic_error_display() {
+    let input = "\
+@Relation data
+@Attribute x NUMERIC
+@Attribute y {a, b}
+@Data
+1, a
+2, c
+";
+
+    let err = DataSet::from_str(input).unwrap_err();
+    assert_eq!(err.attribute(), Some("y"));
+    assert_eq!(
+        err.to_string(),
+        "line 6, column 4 (attribute 'y'): wrong nominal value 'c'"
+    );
+}
+
+#[test]
+fn dynamic_lenient() {
+    let input = "\
+@Relation data
+@Attribute x NUMERIC
+@Attribute y {a, b}
+@Data
+1, a
+2, c
+abc, b
+4, b
+";
+
+    let (dset, diagnostics) = DataSet::from_str_lenient(input, BadRows::Skip).unwrap();
+    assert_eq!(dset.n_rows(), 2);
+    assert_eq!(dset.item(1, 0).as_u8(), Ok(4));
+    assert_eq!(dset.item(1, 1).as_str(), Ok("b"));
+
+    let lines: Vec<_> = diagnostics.iter().map(|d| d.line).collect();
+    assert_eq!(lines, vec![6, 7]);
+    let attributes: Vec<_> = diagnostics.iter().map(|d| d.attribute.clone()).collect();
+    assert_eq!(attributes, vec![Some("y".to_owned()), Some("x".to_owned())]);
+
+    let (dset, missing) = DataSet::from_str_lenient(input, BadRows::Missing).unwrap();
+    assert_eq!(missing, diagnostics);
+    assert_eq!(dset.n_rows(), 4);
+    assert_eq!(dset.weights().len(), 4);
+    assert_eq!(dset.item(1, 0), Value::Missing);
+    assert_eq!(dset.item(1, 1), Value::Missing);
+    assert_eq!(dset.item(3, 0).as_u8(), Ok(4));
+
+    assert!(DataSet::from_str(input).is_err());
+}
+
+#[test]
+fn dynamic_lenient_types() {
+    // values parsed from a bad row must not promote the types of the good data
+    let input = "\
+@Relation data
+@Attribute a INTEGER
+@Attribute b NUMERIC
+@Data
+1, 2
+1000, abc
+1.5, x
+3, 4
+";
+    let good = "@Relation data\n@Attribute a INTEGER\n@Attribute b NUMERIC\n@Data\n1, 2\n3, 4\n";
+
+    let (dset, diagnostics) = DataSet::from_str_lenient(input, BadRows::Skip).unwrap();
+    assert_eq!(diagnostics.len(), 2);
+    assert_eq!(dset, DataSet::from_str(good).unwrap());
+    assert_eq!(dset.col(0).data().get_type(), ColumnType::U8);
+    assert_eq!(dset.schema(), DataSet::from_str(good).unwrap().schema());
+}
+
+#[test]
+fn dynamic_missing_first() {
+    // leading missing values do not decide the type of a numeric column
+    let dset = DataSet::from_str("@RELATION x\n@ATTRIBUTE a NUMERIC\n@DATA\n?\n-5\n").unwrap();
+    assert_eq!(dset.col(0).data().get_type(), ColumnType::I8);
+    assert_eq!(dset.item(1, 0).as_i8(), Ok(-5));
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn dynamic_parallel() {
+    let mut input = String::from(
+        "@RELATION big\n\
+         @ATTRIBUTE x NUMERIC\n\
+         @ATTRIBUTE y NUMERIC\n\
+         @ATTRIBUTE name STRING\n\
+         @ATTRIBUTE class {a, b}\n\
+         @DATA\n",
+    );
+    for i in 0..100_000 {
+        // small unsigned values first, then negative ones, to promote the column
+        let x = if i < 50_000 { i % 300 } else { -(i % 100) };
+        if i % 10_000 == 0 {
+            input += "% checkpoint\n";
+        }
+        input += &format!("{}, {}.5, 'n{}', {}\n", x, i, i, ["a", "b"][i as usize % 2]);
+        if i % 7 == 0 {
+            input += "?, ?, ?, ?, {0.5}\n";
+        }
+    }
+
+    let sequential = DataSet::from_str(&input).unwrap();
+    let parallel = DataSet::from_str_parallel(&input).unwrap();
+    assert_eq!(parallel, sequential);
+    assert_eq!(parallel.col(0).data().get_type(), column::ColumnType::I32);
+
+    // the error is reported at the same position
+    input += "1, 2, three, c\n";
+    input += "x, 2, four, a\n";
+    let sequential = DataSet::from_str(&input).unwrap_err();
+    let parallel = DataSet::from_str_parallel(&input).unwrap_err();
+    assert_eq!(parallel, sequential);
+    assert_eq!(parallel.pos().unwrap().line(), 114_303);
+}
+
+#[test]
+fn dynamic_numeric_types() {
+    use parser::DType;
+
+    let input = "\
+@Relation data
+@Attribute n INTEGER
+@Attribute x REAL
+@Attribute y NUMERIC
+@Data
+1, 2, 3
+3.0, 4, 5
+";
+
+    let dset = DataSet::from_str_strict(input).unwrap();
+    assert_eq!(dset.col(0).data().get_type(), ColumnType::U8);
+    assert_eq!(dset.col(1).data().get_type(), ColumnType::F64);
+    assert_eq!(dset.col(2).data().get_type(), ColumnType::U8);
+    assert_eq!(dset.item(1, 0).as_u8(), Ok(3));
+
+    let dtypes: Vec<_> = dset.schema().attrs.into_iter().map(|a| a.dtype).collect();
+    assert_eq!(dtypes, vec![DType::Integer, DType::Real, DType::Numeric]);
+
+    let built = DataSet::new(
+        "data",
+        vec![
+            Column::new_integer(
+                "n",
+                ColumnData::U8 {
+                    values: vec![Some(1), Some(3)],
+                },
+            ),
+            Column::new_real(
+                "x",
+                ColumnData::F64 {
+                    values: vec![Some(2.0), Some(4.0)],
+                },
+            ),
+            Column::new(
+                "y",
+                ColumnData::U8 {
+                    values: vec![Some(3), Some(5)],
+                },
+            ),
+        ],
+    );
+    assert_eq!(built, dset);
+    assert_eq!(built.schema(), dset.schema());
+
+    let input = input.replace("3.0,", "1.5,");
+    assert_eq!(
+        DataSet::from_str_strict(&input).unwrap_err().to_string(),
+        "line 7, column 1 (attribute 'n'): expected integer, found '1.5'"
+    );
+
+    let dset = DataSet::from_str(&input).unwrap();
+    assert_eq!(dset.col(0).data().get_type(), ColumnType::F64);
+    assert_eq!(dset.item(1, 0).as_f64(), Ok(1.5));
+    assert_eq!(dset.schema().attrs[0].dtype, DType::Numeric);
+}
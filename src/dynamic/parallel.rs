@@ -0,0 +1,127 @@
+// Copyright 2018 Martin Billinger
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Multi-threaded parsing of the data section, with the `parallel` feature.
+
+use std::cmp;
+
+use rayon::prelude::*;
+
+use error::Result;
+use parser::Parser;
+
+use super::dataset::DataSet;
+
+/// number of chunks per thread, so that threads that finish early can take over more work
+const CHUNKS_PER_THREAD: usize = 4;
+
+/// smaller chunks are not worth the overhead of parsing and merging them separately
+const MIN_CHUNK_SIZE: usize = 64 * 1024;
+
+impl DataSet {
+    /// Deserialize an instance of type `DataSet` from an ARFF formatted string, parsing the data
+    /// rows on multiple threads.
+    ///
+    /// The data section is split into chunks of whole lines, which are parsed in parallel and
+    /// then concatenated. Rows must therefore not span multiple lines, as Weka requires anyway.
+    /// The result is the same as with `from_str`, however the rows are split. If several rows
+    /// fail to parse, the error of the first one is returned.
+    pub fn from_str_parallel(input: &str) -> Result<Self> {
+        let mut parser = Parser::new(input);
+        let header = parser.parse_header()?;
+        let first_line = parser.pos().line();
+        let data = parser.remaining().unwrap_or("");
+
+        let template = DataSet::from_attributes(header.name, header.attrs)?;
+        let mut dset = template.clone();
+        dset.set_relation_comments(header.relation_comments);
+        dset.set_comments(header.comments);
+
+        let threads = ::rayon::current_num_threads();
+        let chunks = split_lines(data, threads * CHUNKS_PER_THREAD);
+        let n_lines: Vec<usize> = chunks.par_iter().map(|chunk| count_lines(chunk)).collect();
+        let first_lines = n_lines.iter().scan(first_line, |line, n| {
+            let first = *line;
+            *line += n;
+            Some(first)
+        });
+        let jobs: Vec<_> = chunks.into_iter().zip(first_lines).collect();
+
+        let parts: Vec<Result<DataSet>> = jobs
+            .into_par_iter()
+            .map(|(chunk, line)| {
+                let mut part = template.clone();
+                part.parse_rows(&mut Parser::with_first_line(chunk, line))?;
+                Ok(part)
+            })
+            .collect();
+
+        for part in parts {
+            dset.append(&mut part?);
+        }
+        Ok(dset)
+    }
+}
+
+/// split text into about `n` chunks that end at a newline, except for the last one
+fn split_lines(text: &str, n: usize) -> Vec<&str> {
+    let size = cmp::max(text.len() / cmp::max(n, 1), MIN_CHUNK_SIZE);
+    let mut chunks = Vec::new();
+    let mut rest = text;
+    while rest.len() > size {
+        match rest.as_bytes()[size..].iter().position(|&ch| ch == b'\n') {
+            Some(i) => {
+                let (chunk, tail) = rest.split_at(size + i + 1);
+                chunks.push(chunk);
+                rest = tail;
+            }
+            None => break,
+        }
+    }
+    chunks.push(rest);
+    chunks
+}
+
+fn count_lines(text: &str) -> usize {
+    text.bytes().filter(|&ch| ch == b'\n').count()
+}
+
+#[test]
+fn test_split_lines() {
+    let text = "a\nbb\nccc\n".repeat(MIN_CHUNK_SIZE / 3);
+    let chunks = split_lines(&text, 8);
+
+    assert!(chunks.len() > 1);
+    assert_eq!(chunks.concat(), text);
+    for chunk in &chunks[..chunks.len() - 1] {
+        assert!(chunk.ends_with('\n'));
+    }
+    assert_eq!(split_lines("a\nb", 8), vec!["a\nb"]);
+}
+
+#[test]
+fn test_chunks_before_missing_values() {
+    use super::column::ColumnType;
+
+    let input = "@RELATION x\n@ATTRIBUTE a NUMERIC\n@DATA\n-1\n?\n-5\n";
+    let expected = DataSet::from_str(input).unwrap();
+    assert_eq!(expected.col(0).data().get_type(), ColumnType::I8);
+
+    // parse the rows in two chunks, with the boundary right before the missing value
+    let mut parser = Parser::new(input);
+    let header = parser.parse_header().unwrap();
+    let template = DataSet::from_attributes(header.name, header.attrs).unwrap();
+    let mut dset = template.clone();
+    for &(chunk, line) in &[("-1\n", 4), ("?\n-5\n", 5)] {
+        let mut part = template.clone();
+        part.parse_rows(&mut Parser::with_first_line(chunk, line))
+            .unwrap();
+        dset.append(&mut part);
+    }
+    assert_eq!(dset, expected);
+}
@@ -1,5 +1,10 @@
+use serde::ser::{self, Serialize, Serializer};
+
+use date::DATE_TOKEN;
 use error::{Error, Result};
 
+use super::dataset::DataSet;
+
 /// a dynamically typed ARFF value
 #[derive(Debug, PartialEq)]
 pub enum Value<'a> {
@@ -14,7 +19,37 @@ pub enum Value<'a> {
     I64(i64),
     F64(f64),
     String(&'a str),
+    /// milliseconds since the Unix epoch
+    Date(i64),
     Nominal(usize, &'a Vec<String>),
+    /// the nested instances of a relational attribute
+    Relational(&'a DataSet),
+}
+
+impl<'a> Serialize for Value<'a> {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match *self {
+            Value::Missing => serializer.serialize_none(),
+            Value::U8(v) => serializer.serialize_u8(v),
+            Value::U16(v) => serializer.serialize_u16(v),
+            Value::U32(v) => serializer.serialize_u32(v),
+            Value::U64(v) => serializer.serialize_u64(v),
+            Value::I8(v) => serializer.serialize_i8(v),
+            Value::I16(v) => serializer.serialize_i16(v),
+            Value::I32(v) => serializer.serialize_i32(v),
+            Value::I64(v) => serializer.serialize_i64(v),
+            Value::F64(v) => serializer.serialize_f64(v),
+            Value::String(s) => serializer.serialize_str(s),
+            Value::Date(millis) => serializer.serialize_newtype_struct(DATE_TOKEN, &millis),
+            Value::Nominal(idx, categories) => serializer.serialize_str(&categories[idx]),
+            Value::Relational(_) => Err(ser::Error::custom(
+                "relational values can not be serialized",
+            )),
+        }
+    }
 }
 
 impl<'a> From<u8> for Value<'a> {
@@ -142,6 +177,8 @@ impl<'a> Value<'a> {
             Value::I32(x) => Ok(x > 0),
             Value::I64(x) => Ok(x > 0),
             Value::F64(x) => Ok(x > 0.0),
+            Value::Date(_) => Err(Error::ConversionError),
+            Value::Relational(_) => Err(Error::ConversionError),
             Value::String(s) => Ok(s.eq_ignore_ascii_case("true")
                 || s.eq_ignore_ascii_case("yes")
                 || s.eq_ignore_ascii_case("y")
@@ -256,6 +293,7 @@ impl<'a> Value<'a> {
             Value::I16(x) => Ok(x as i64),
             Value::I32(x) => Ok(x as i64),
             Value::I64(x) => Ok(x),
+            Value::Date(x) => Ok(x),
             Value::Nominal(i @ 0...2147483647, _) => Ok(i as i64),
             _ => Err(Error::ConversionError),
         }
@@ -273,6 +311,7 @@ impl<'a> Value<'a> {
             Value::I32(x) => Ok(x as f64),
             Value::I64(x) => Ok(x as f64),
             Value::F64(x) => Ok(x),
+            Value::Date(x) => Ok(x as f64),
             Value::Nominal(i, _) => Ok(i as f64),
             _ => Err(Error::ConversionError),
         }
@@ -8,6 +8,7 @@
 
 use std;
 use std::fmt::{self, Display};
+use std::io;
 use std::string::FromUtf8Error;
 
 use serde::{de, ser};
@@ -22,29 +23,90 @@ pub enum Error {
 
     // Serializer
     UnexpectedType,
-    InconsistentType { row: usize, column: usize },
+    InconsistentType {
+        row: usize,
+        column: usize,
+    },
+    WrongRowLength {
+        row: usize,
+        expected: usize,
+    },
+    WrongColumnName {
+        column: usize,
+        expected: String,
+        found: String,
+    },
+    UndeclaredNominalValue {
+        row: usize,
+        column: usize,
+        value: String,
+    },
+    RelationalAttribute(String),
 
     // Deserializer
     Eof,
+    Row {
+        line: usize,
+        error: Box<Error>,
+    },
+    Attribute {
+        name: String,
+        pos: Option<TextPos>,
+        error: Box<Error>,
+    },
     Expected(TextPos, &'static str),
     ExpectedString(TextPos, String),
     UnexpectedChar(TextPos, char, char),
     ExpectedSequenceType,
-    ExpectedUnsignedValue(TextPos),
-    ExpectedIntegerValue(TextPos),
-    ExpectedFloatValue(TextPos),
+    ExpectedUnsignedValue(TextPos, String),
+    ExpectedIntegerValue(TextPos, String),
+    ExpectedFloatValue(TextPos, String),
     NumericRange(TextPos, i64, i64),
     NumericOverflow(TextPos),
     Utf8Error(std::str::Utf8Error),
+    Io(io::ErrorKind, String),
 
     InvalidColumnType(TextPos, String),
     WrongNominalValue(TextPos, String),
     UnsupportedColumnType(TextPos, String),
+    InvalidDateFormat(String),
+    InvalidDate(TextPos, String),
+    Xrff(TextPos, String),
 
     ConversionError,
     UnexpectedMissingValue,
 }
 
+/// A data row that could not be parsed in lenient mode
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    /// line of the row in the input, starting at 1
+    pub line: usize,
+    /// name of the attribute whose value could not be parsed, if known
+    pub attribute: Option<String>,
+    /// the reason why the row could not be parsed
+    pub error: Error,
+}
+
+impl Diagnostic {
+    pub(crate) fn new(line: usize, error: Error) -> Self {
+        Diagnostic {
+            line,
+            attribute: error.attribute().map(str::to_owned),
+            error,
+        }
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self.error.pos() {
+            Some(_) => write!(formatter, "{}", self.error),
+            None => write!(formatter, "line {}: {}", self.line, self.error),
+        }
+    }
+}
+
 impl ser::Error for Error {
     fn custom<T: Display>(msg: T) -> Self {
         Error::Message(msg.to_string())
@@ -57,9 +119,160 @@ impl de::Error for Error {
     }
 }
 
+impl Error {
+    /// position in the input at which the error occurred, if known
+    pub fn pos(&self) -> Option<TextPos> {
+        match *self {
+            Error::Row { ref error, .. } => error.pos(),
+            Error::Attribute { pos, ref error, .. } => pos.or_else(|| error.pos()),
+            Error::Expected(pos, _)
+            | Error::ExpectedString(pos, _)
+            | Error::UnexpectedChar(pos, _, _)
+            | Error::ExpectedUnsignedValue(pos, _)
+            | Error::ExpectedIntegerValue(pos, _)
+            | Error::ExpectedFloatValue(pos, _)
+            | Error::NumericRange(pos, _, _)
+            | Error::NumericOverflow(pos)
+            | Error::InvalidColumnType(pos, _)
+            | Error::WrongNominalValue(pos, _)
+            | Error::UnsupportedColumnType(pos, _)
+            | Error::InvalidDate(pos, _)
+            | Error::Xrff(pos, _) => Some(pos),
+            _ => None,
+        }
+    }
+
+    /// name of the attribute whose value caused the error, if known
+    pub fn attribute(&self) -> Option<&str> {
+        match *self {
+            Error::Row { ref error, .. } => error.attribute(),
+            Error::Attribute { ref name, .. } => Some(name),
+            _ => None,
+        }
+    }
+
+    /// Render the error followed by the offending line of `source` and a marker below the
+    /// position of the error.
+    ///
+    /// `source` is the complete input the error originates from. If the error has no position,
+    /// this is the same as `to_string()`.
+    pub fn display_with_source(&self, source: &str) -> String {
+        let mut msg = self.to_string();
+        let pos = match self.pos() {
+            Some(pos) => pos,
+            None => return msg,
+        };
+        let line = match source.lines().nth(pos.line().saturating_sub(1)) {
+            Some(line) => line.trim_end_matches('\r'),
+            None => return msg,
+        };
+        // keep tabs, so that the marker lines up with the excerpt
+        let indent: String = line
+            .char_indices()
+            .take_while(|&(i, _)| i + 1 < pos.column())
+            .map(|(_, ch)| if ch == '\t' { '\t' } else { ' ' })
+            .collect();
+        msg += "\n";
+        msg += line;
+        msg += "\n";
+        msg += &indent;
+        msg += "^";
+        msg
+    }
+
+    /// write the message of the error without position or attribute
+    fn fmt_message(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Row { ref error, .. } | Error::Attribute { ref error, .. } => {
+                error.fmt_message(f)
+            }
+            Error::InconsistentType { row, column } => write!(
+                f,
+                "inconsistent data type in row {}, column {}",
+                row, column
+            ),
+            Error::WrongRowLength { row, expected } => write!(
+                f,
+                "row {} does not have {} values as declared in the header",
+                row, expected
+            ),
+            Error::WrongColumnName {
+                column,
+                ref expected,
+                ref found,
+            } => write!(
+                f,
+                "column {} is named '{}' in the header, found '{}'",
+                column, expected, found
+            ),
+            Error::UndeclaredNominalValue {
+                row,
+                column,
+                ref value,
+            } => write!(
+                f,
+                "nominal value '{}' in row {}, column {} is not declared in the header",
+                value, row, column
+            ),
+            Error::RelationalAttribute(ref name) => {
+                write!(f, "cannot write RELATIONAL attribute '{}'", name)
+            }
+            Error::Expected(_, what) => write!(f, "expected {}", what),
+            Error::ExpectedString(_, ref what) => write!(f, "expected {}", what),
+            Error::UnexpectedChar(_, expected, found) => {
+                write!(f, "expected {:?}, found ", expected)?;
+                match found {
+                    '\0' => f.write_str("end of input"),
+                    _ => write!(f, "{:?}", found),
+                }
+            }
+            Error::ExpectedUnsignedValue(_, ref found) => {
+                write!(f, "expected unsigned integer, found '{}'", found)
+            }
+            Error::ExpectedIntegerValue(_, ref found) => {
+                write!(f, "expected integer, found '{}'", found)
+            }
+            Error::ExpectedFloatValue(_, ref found) => {
+                write!(f, "expected float, found '{}'", found)
+            }
+            Error::NumericRange(_, min, max) => {
+                write!(f, "value outside numeric range [{}, {}]", min, max)
+            }
+            Error::Utf8Error(ref e) => write!(f, "invalid UTF-8 string: {}", e),
+            Error::InvalidColumnType(_, ref t) => write!(f, "column type '{}' not understood", t),
+            Error::WrongNominalValue(_, ref v) => write!(f, "wrong nominal value '{}'", v),
+            Error::UnsupportedColumnType(_, ref t) => {
+                write!(f, "column type '{}' not supported", t)
+            }
+            Error::InvalidDateFormat(ref fmt) => write!(f, "date format '{}' not supported", fmt),
+            Error::InvalidDate(_, ref date) => write!(f, "date '{}' does not match format", date),
+            _ => f.write_str(std::error::Error::description(self)),
+        }
+    }
+}
+
 impl Display for Error {
+    /// Errors are prefixed with their position and attribute if known, e.g.
+    /// `line 3, column 7 (attribute 'width'): expected float, found 'abc'`.
     fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        formatter.write_str(std::error::Error::description(self))
+        let prefixed = match (self.pos(), self) {
+            (Some(pos), _) => {
+                write!(formatter, "{}", pos)?;
+                true
+            }
+            (None, &Error::Row { line, .. }) => {
+                write!(formatter, "line {}", line)?;
+                true
+            }
+            _ => false,
+        };
+        match (prefixed, self.attribute()) {
+            (true, Some(name)) => write!(formatter, " (attribute '{}'): ", name)?,
+            (true, None) => formatter.write_str(": ")?,
+            (false, Some(name)) => write!(formatter, "attribute '{}': ", name)?,
+            (false, None) => {}
+        }
+        self.fmt_message(formatter)
     }
 }
 
@@ -69,24 +282,42 @@ impl std::error::Error for Error {
             Error::Message(ref msg) => msg,
             Error::UnexpectedType => "unexpected data type",
             Error::InconsistentType { .. } => "inconsistent data type",
+            Error::WrongRowLength { .. } => "number of values does not match the header",
+            Error::WrongColumnName { .. } => "column name does not match the header",
+            Error::UndeclaredNominalValue { .. } => "nominal value not declared in the header",
+            Error::RelationalAttribute(_) => "cannot write RELATIONAL attribute",
             Error::Eof => "unexpected end of input",
+            Error::Row { .. } => "invalid data row",
+            Error::Attribute { .. } => "invalid attribute value",
             Error::Expected(_, ref what) => what,
             Error::ExpectedString(_, ref what) => what,
             Error::UnexpectedChar(_, _, _) => "unexpected character",
-            Error::ExpectedUnsignedValue(_) => "expected unsigned integer value",
-            Error::ExpectedIntegerValue(_) => "expected integer value",
+            Error::ExpectedUnsignedValue(_, _) => "expected unsigned integer value",
+            Error::ExpectedIntegerValue(_, _) => "expected integer value",
             Error::NumericRange(_, _, _) => "value outside numeric range",
             Error::NumericOverflow(_) => "value too large for u64",
             Error::ExpectedSequenceType => "attempt to parse data set as a non-sequence type",
-            Error::ExpectedFloatValue(_) => "invalid floating point number",
+            Error::ExpectedFloatValue(_, _) => "invalid floating point number",
             Error::Utf8Error(_) => "invalid UTF-8 string",
+            Error::Io(_, ref msg) => msg,
             Error::InvalidColumnType(_, _) => "column type not understood",
             Error::UnsupportedColumnType(_, _) => "column type not supported",
             Error::WrongNominalValue(_, _) => "wrong nominal value",
+            Error::InvalidDateFormat(_) => "date format not supported",
+            Error::InvalidDate(_, _) => "date does not match format",
+            Error::Xrff(_, ref msg) => msg,
             Error::ConversionError => "conversion error",
             Error::UnexpectedMissingValue => "unexpected missing value",
         }
     }
+
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match *self {
+            Error::Row { ref error, .. } | Error::Attribute { ref error, .. } => Some(&**error),
+            Error::Utf8Error(ref e) => Some(e),
+            _ => None,
+        }
+    }
 }
 
 impl From<FromUtf8Error> for Error {
@@ -94,3 +325,9 @@ impl From<FromUtf8Error> for Error {
         Error::Utf8Error(e.utf8_error())
     }
 }
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error::Io(e.kind(), e.to_string())
+    }
+}
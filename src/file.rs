@@ -0,0 +1,208 @@
+// Copyright 2018 Martin Billinger
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Read and write ARFF files, optionally gzip compressed.
+//!
+//! Compressed input is detected by the gzip magic bytes, compressed output by the `.gz`
+//! extension of the path. (De)compression requires the `gzip` feature. With the `mmap` feature,
+//! uncompressed files can be memory-mapped instead of read through a buffer.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+#[cfg(feature = "gzip")]
+use flate2::{bufread::MultiGzDecoder, write::GzEncoder, Compression};
+#[cfg(feature = "mmap")]
+use memmap2::Mmap;
+use serde::de::DeserializeOwned;
+use serde::ser::Serialize;
+
+use super::de::from_reader;
+use super::error::Result;
+use super::ser::to_writer;
+
+/// The first two bytes of every gzip stream
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Open a file for reading, decompressing it if it is gzip compressed.
+pub fn open_file<P: AsRef<Path>>(path: P) -> Result<Box<dyn BufRead>> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let is_gzip = reader.fill_buf()?.starts_with(&GZIP_MAGIC);
+    if is_gzip {
+        decompress(reader)
+    } else {
+        Ok(Box::new(reader))
+    }
+}
+
+/// Memory-map a file for reading, unless it is gzip compressed.
+///
+/// Returns `None` for compressed files, which need to go through `open_file` instead.
+#[cfg(feature = "mmap")]
+pub(crate) fn map_file<P: AsRef<Path>>(path: P) -> Result<Option<Mmap>> {
+    let file = File::open(path)?;
+    // The mapping is only valid as long as no other process truncates or modifies the file.
+    // This is the usual caveat of memory-mapped files, and documented at `DataSet::from_path`.
+    let map = unsafe { Mmap::map(&file)? };
+    if map.starts_with(&GZIP_MAGIC) {
+        Ok(None)
+    } else {
+        Ok(Some(map))
+    }
+}
+
+/// Deserialize an instance of type `T` from an ARFF file, which may be gzip compressed.
+pub fn from_path<P, T>(path: P) -> Result<T>
+where
+    P: AsRef<Path>,
+    T: DeserializeOwned,
+{
+    from_reader(open_file(path)?)
+}
+
+/// Serialize an instance of type `T` into an ARFF file.
+///
+/// The output is gzip compressed if the file name ends with `.gz`.
+pub fn to_path<P, T>(path: P, value: &T) -> Result<()>
+where
+    P: AsRef<Path>,
+    T: Serialize,
+{
+    let path = path.as_ref();
+    if path.extension().and_then(|ext| ext.to_str()) == Some("gz") {
+        compress(path, |writer| to_writer(writer, value))
+    } else {
+        to_writer(BufWriter::new(File::create(path)?), value)
+    }
+}
+
+#[cfg(feature = "gzip")]
+fn decompress(reader: BufReader<File>) -> Result<Box<dyn BufRead>> {
+    Ok(Box::new(BufReader::new(MultiGzDecoder::new(reader))))
+}
+
+#[cfg(not(feature = "gzip"))]
+fn decompress(_reader: BufReader<File>) -> Result<Box<dyn BufRead>> {
+    Err(gzip_disabled().into())
+}
+
+#[cfg(feature = "gzip")]
+fn compress<F>(path: &Path, write: F) -> Result<()>
+where
+    F: FnOnce(&mut dyn Write) -> Result<()>,
+{
+    let file = BufWriter::new(File::create(path)?);
+    let mut encoder = GzEncoder::new(file, Compression::default());
+    write(&mut encoder)?;
+    encoder.finish()?.flush()?;
+    Ok(())
+}
+
+/// fails before the file is created, so that an existing file is left untouched
+#[cfg(not(feature = "gzip"))]
+fn compress<F>(_path: &Path, _write: F) -> Result<()>
+where
+    F: FnOnce(&mut dyn Write) -> Result<()>,
+{
+    Err(gzip_disabled().into())
+}
+
+#[cfg(not(feature = "gzip"))]
+fn gzip_disabled() -> ::std::io::Error {
+    use std::io;
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        "gzip compressed files require the `gzip` feature",
+    )
+}
+
+#[cfg(test)]
+fn temp_path(name: &str) -> ::std::path::PathBuf {
+    ::std::env::temp_dir().join(format!("arff-{}-{}", ::std::process::id(), name))
+}
+
+#[test]
+fn test_plain_file() {
+    use dynamic::DataSet;
+
+    let data = vec![(1, 2.5), (3, -1.0)];
+    let path = temp_path("plain.arff");
+
+    to_path(&path, &data).unwrap();
+    let rows: Vec<(u8, f64)> = from_path(&path).unwrap();
+    let dset = DataSet::from_path(&path).unwrap();
+    ::std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(rows, data);
+    assert_eq!(dset.n_rows(), 2);
+}
+
+#[cfg(feature = "gzip")]
+#[test]
+fn test_gzip_file() {
+    use dynamic::DataSet;
+    use std::fs;
+
+    let data = vec![(1, 2.5), (3, -1.0)];
+    let path = temp_path("compressed.arff.gz");
+
+    to_path(&path, &data).unwrap();
+    let raw = fs::read(&path).unwrap();
+    let rows: Vec<(u8, f64)> = from_path(&path).unwrap();
+    let dset = DataSet::from_path(&path).unwrap();
+
+    // compression is detected from the content, not the file name
+    let renamed = temp_path("compressed.arff");
+    fs::rename(&path, &renamed).unwrap();
+    let renamed_rows: Vec<(u8, f64)> = from_path(&renamed).unwrap();
+    fs::remove_file(&renamed).unwrap();
+
+    assert!(raw.starts_with(&GZIP_MAGIC));
+    assert_eq!(rows, data);
+    assert_eq!(renamed_rows, data);
+    assert_eq!(dset.n_rows(), 2);
+}
+
+#[cfg(not(feature = "gzip"))]
+#[test]
+fn test_gzip_disabled() {
+    use error::Error;
+    use std::io;
+
+    let path = temp_path("disabled.arff.gz");
+    ::std::fs::write(&path, "existing content").unwrap();
+    let result = to_path(&path, &vec![(1, 2)]);
+    let content = ::std::fs::read_to_string(&path).unwrap();
+    ::std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(content, "existing content");
+
+    match result {
+        Err(Error::Io(kind, _)) => assert_eq!(kind, io::ErrorKind::InvalidInput),
+        r => panic!("expected an I/O error, got {:?}", r),
+    }
+}
+
+#[cfg(feature = "mmap")]
+#[test]
+fn test_mapped_invalid_utf8() {
+    use dynamic::DataSet;
+    use error::Error;
+    use std::fs;
+
+    let path = temp_path("invalid.arff");
+    fs::write(&path, b"@RELATION x\n@ATTRIBUTE s STRING\n@DATA\n'a\xff'\n").unwrap();
+    let result = DataSet::from_path(&path);
+    fs::remove_file(&path).unwrap();
+
+    match result {
+        Err(Error::Utf8Error(e)) => assert_eq!(e.valid_up_to(), 40),
+        r => panic!("expected a UTF-8 error, got {:?}", r),
+    }
+}
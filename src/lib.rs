@@ -57,22 +57,42 @@
 //! }
 //! ```
 
+#[cfg(feature = "gzip")]
+extern crate flate2;
+#[cfg(feature = "mmap")]
+extern crate memmap2;
 extern crate num_traits;
+#[cfg(feature = "parallel")]
+extern crate rayon;
 extern crate serde;
+#[cfg(feature = "xrff")]
+extern crate xml;
 
 #[cfg(test)]
 #[macro_use]
 extern crate serde_derive;
 
+mod date;
 mod de;
 pub mod dynamic;
 mod error;
+mod file;
 mod parser;
 mod ser;
-
-pub use de::{flat_from_str, from_str, Deserializer};
-pub use error::{Error, Result};
-pub use ser::{to_string, Serializer};
+mod weighted;
+#[cfg(feature = "xrff")]
+pub mod xrff;
+
+pub use date::{Date, DateFormat};
+pub use de::{
+    flat_from_str, from_reader, from_reader_lenient, from_str, from_str_lenient, from_str_strict,
+    read_header, rows, Deserializer, StreamDeserializer,
+};
+pub use error::{Diagnostic, Error, Result};
+pub use file::{from_path, open_file, to_path};
+pub use parser::{Attribute, DType, Header, TextPos};
+pub use ser::{to_string, to_string_sparse, to_writer, to_writer_sparse, ArffWriter, Serializer};
+pub use weighted::Weighted;
 
 #[cfg(test)]
 mod tests {
@@ -125,8 +145,8 @@ mod tests {
 
         let input = "@RELATION MyData
 
-@ATTRIBUTE a NUMERIC
-@ATTRIBUTE b NUMERIC
+@ATTRIBUTE a INTEGER
+@ATTRIBUTE b REAL
 @ATTRIBUTE c STRING
 
 @DATA
@@ -202,6 +222,294 @@ mod tests {
         assert_eq!(deser, orig);
     }
 
+    #[test]
+    fn roundtrip_date() {
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        struct Row {
+            when: Date,
+            what: String,
+        }
+
+        let orig = vec![
+            Row {
+                when: Date::from_ymd_hms(1999, 12, 31, 23, 59, 59).unwrap(),
+                what: "party".to_owned(),
+            },
+            Row {
+                when: Date::from_ymd_hms(2000, 1, 1, 0, 0, 0).unwrap(),
+                what: "hangover".to_owned(),
+            },
+        ];
+
+        let arff = to_string(&orig).unwrap();
+        let deser: Vec<Row> = from_str(&arff).unwrap();
+
+        assert_eq!(deser, orig);
+    }
+
+    #[test]
+    fn roundtrip_sparse() {
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        enum Class {
+            Spam,
+            Ham,
+        }
+
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        struct Row {
+            a: u32,
+            b: u32,
+            c: i64,
+            score: Option<f64>,
+            flag: bool,
+            class: Class,
+        }
+
+        let orig = vec![
+            Row {
+                a: 0,
+                b: 3,
+                c: 0,
+                score: Some(0.0),
+                flag: false,
+                class: Class::Ham,
+            },
+            Row {
+                a: 1,
+                b: 0,
+                c: -7,
+                score: None,
+                flag: true,
+                class: Class::Spam,
+            },
+        ];
+
+        let arff = to_string_sparse(&orig).unwrap();
+        let deser: Vec<Row> = from_str(&arff).unwrap();
+
+        assert_eq!(deser, orig);
+    }
+
+    #[test]
+    fn roundtrip_weights() {
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        struct Row {
+            x: f64,
+            label: String,
+        }
+
+        let orig = vec![
+            Weighted::new(
+                Row {
+                    x: 1.5,
+                    label: "one".to_owned(),
+                },
+                0.125,
+            ),
+            Weighted::new(
+                Row {
+                    x: 0.0,
+                    label: "two".to_owned(),
+                },
+                1.0,
+            ),
+        ];
+
+        let arff = to_string(&orig).unwrap();
+        let deser: Vec<Weighted<Row>> = from_str(&arff).unwrap();
+        assert_eq!(deser, orig);
+
+        let arff = to_string_sparse(&orig).unwrap();
+        let deser: Vec<Weighted<Row>> = from_str(&arff).unwrap();
+        assert_eq!(deser, orig);
+    }
+
+    #[test]
+    fn roundtrip_escapes() {
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        enum Place {
+            #[serde(rename = "New York")]
+            NewYork,
+            #[serde(rename = "it's")]
+            Quoted,
+        }
+
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        struct Row {
+            text: String,
+            place: Place,
+        }
+
+        let orig = vec![
+            Row {
+                text: "'quoted' \"double\" back\\slash".to_owned(),
+                place: Place::NewYork,
+            },
+            Row {
+                text: "line\nbreak\ttab % not a comment, {not sparse}".to_owned(),
+                place: Place::Quoted,
+            },
+            Row {
+                text: "?".to_owned(),
+                place: Place::NewYork,
+            },
+        ];
+
+        let arff = to_string(&orig).unwrap();
+        let deser: Vec<Row> = from_str(&arff).unwrap();
+        assert_eq!(deser, orig);
+
+        let arff = to_string_sparse(&orig).unwrap();
+        let deser: Vec<Row> = from_str(&arff).unwrap();
+        assert_eq!(deser, orig);
+    }
+
+    #[test]
+    fn roundtrip_nominal_categories() {
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        enum City {
+            #[serde(rename = "Washington, D.C.")]
+            Washington,
+            #[serde(rename = "New York")]
+            NewYork,
+        }
+
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        struct Row {
+            city: City,
+        }
+
+        let orig = vec![
+            Row {
+                city: City::Washington,
+            },
+            Row {
+                city: City::NewYork,
+            },
+        ];
+
+        let arff = to_string(&orig).unwrap();
+        let deser: Vec<Row> = from_str(&arff).unwrap();
+        assert_eq!(deser, orig);
+
+        let dset = dynamic::DataSet::from_str(&arff).unwrap();
+        assert_eq!(dset.item(0, 0).as_str(), Ok("Washington, D.C."));
+        assert_eq!(dset.item(1, 0).as_str(), Ok("New York"));
+    }
+
+    #[test]
+    fn roundtrip_quoted_names() {
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        struct Row {
+            #[serde(rename = "first name")]
+            name: String,
+            #[serde(rename = "it's 100%")]
+            value: f64,
+        }
+
+        let orig = vec![
+            Row {
+                name: "x".to_owned(),
+                value: 1.0,
+            },
+            Row {
+                name: "y".to_owned(),
+                value: 0.5,
+            },
+        ];
+
+        let arff = to_string(&orig).unwrap();
+        let deser: Vec<Row> = from_str(&arff).unwrap();
+        assert_eq!(deser, orig);
+
+        let dset = dynamic::DataSet::from_str(&arff).unwrap();
+        assert_eq!(
+            dset.col_names().collect::<Vec<_>>(),
+            vec!["first name", "it's 100%"]
+        );
+    }
+
+    #[test]
+    fn append_rows() {
+        use std::io::Cursor;
+
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        enum Status {
+            #[serde(rename = "ok")]
+            Ok,
+            #[serde(rename = "failed")]
+            Failed,
+            #[serde(rename = "unknown")]
+            Unknown,
+        }
+
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        struct Row {
+            day: u32,
+            status: Status,
+        }
+
+        let existing = "@RELATION jobs
+@ATTRIBUTE day NUMERIC
+@ATTRIBUTE status {ok, failed}
+@DATA
+1, ok";
+
+        let mut writer = ArffWriter::append(Cursor::new(existing.as_bytes().to_vec())).unwrap();
+        writer
+            .write_row(&Row {
+                day: 2,
+                status: Status::Failed,
+            })
+            .unwrap();
+        assert_eq!(
+            writer.write_row(&Row {
+                day: 3,
+                status: Status::Unknown,
+            }),
+            Err(Error::Attribute {
+                name: "status".to_owned(),
+                pos: None,
+                error: Box::new(Error::UndeclaredNominalValue {
+                    row: 1,
+                    column: 1,
+                    value: "unknown".to_owned(),
+                }),
+            })
+        );
+
+        let more = dynamic::DataSet::from_str(
+            "@RELATION new\n@ATTRIBUTE day NUMERIC\n@ATTRIBUTE status {failed, ok}\n@DATA\n3, ok\n4, failed, {2}\n",
+        ).unwrap();
+        writer.write_dataset(&more).unwrap();
+
+        let wrong = dynamic::DataSet::from_str(
+            "@RELATION new\n@ATTRIBUTE day NUMERIC\n@ATTRIBUTE status STRING\n@DATA\n5, 'ok'\n6, 'maybe'\n",
+        ).unwrap();
+        assert_eq!(
+            writer.write_dataset(&wrong),
+            Err(Error::Attribute {
+                name: "status".to_owned(),
+                pos: None,
+                error: Box::new(Error::UndeclaredNominalValue {
+                    row: 4,
+                    column: 1,
+                    value: "maybe".to_owned(),
+                }),
+            })
+        );
+
+        let output = String::from_utf8(writer.into_inner().into_inner()).unwrap();
+        assert_eq!(
+            output,
+            existing.to_owned() + "\n2, failed\n3, ok\n4, failed, {2}\n"
+        );
+
+        let rows: Vec<Row> = from_str(&output).unwrap();
+        assert_eq!(rows.len(), 4);
+        assert_eq!(rows[3].status, Status::Failed);
+    }
+
     #[test]
     fn type_ser_support_outer() {
         type Row = [i32; 1];
@@ -214,28 +522,28 @@ mod tests {
         assert_eq!(
             to_string(&d_tuple).unwrap(),
             format!(
-                "@RELATION {}\n\n@ATTRIBUTE col1 NUMERIC\n\n@DATA\n1\n2\n",
+                "@RELATION {}\n\n@ATTRIBUTE col1 INTEGER\n\n@DATA\n1\n2\n",
                 "unnamed_data"
             )
         );
         assert_eq!(
             to_string(&d_array).unwrap(),
             format!(
-                "@RELATION {}\n\n@ATTRIBUTE col1 NUMERIC\n\n@DATA\n1\n2\n",
+                "@RELATION {}\n\n@ATTRIBUTE col1 INTEGER\n\n@DATA\n1\n2\n",
                 "unnamed_data"
             )
         );
         assert_eq!(
             to_string(&d_vec).unwrap(),
             format!(
-                "@RELATION {}\n\n@ATTRIBUTE col1 NUMERIC\n\n@DATA\n1\n2\n",
+                "@RELATION {}\n\n@ATTRIBUTE col1 INTEGER\n\n@DATA\n1\n2\n",
                 "unnamed_data"
             )
         );
         assert_eq!(
             to_string(&d_slice).unwrap(),
             format!(
-                "@RELATION {}\n\n@ATTRIBUTE col1 NUMERIC\n\n@DATA\n1\n2\n",
+                "@RELATION {}\n\n@ATTRIBUTE col1 INTEGER\n\n@DATA\n1\n2\n",
                 "unnamed_data"
             )
         );
@@ -246,7 +554,7 @@ mod tests {
         assert_eq!(
             to_string(&d_newtype_struct).unwrap(),
             format!(
-                "@RELATION {}\n\n@ATTRIBUTE col1 NUMERIC\n\n@DATA\n1\n2\n",
+                "@RELATION {}\n\n@ATTRIBUTE col1 INTEGER\n\n@DATA\n1\n2\n",
                 "NewtypeStruct"
             )
         );
@@ -257,7 +565,7 @@ mod tests {
         assert_eq!(
             to_string(&d_tuple_struct).unwrap(),
             format!(
-                "@RELATION {}\n\n@ATTRIBUTE col1 NUMERIC\n\n@DATA\n1\n2\n",
+                "@RELATION {}\n\n@ATTRIBUTE col1 INTEGER\n\n@DATA\n1\n2\n",
                 "TupleStruct"
             )
         );
@@ -275,8 +583,140 @@ mod tests {
         let d_tuple: [(f64, i32); 1] = [(1.1, 2)];
         let d_array: [[f64; 2]; 1] = [[1.1, 2.0]];
 
-        assert_eq!(to_string(&d_struct).unwrap(), "@RELATION unnamed_data\n\n@ATTRIBUTE x NUMERIC\n@ATTRIBUTE y NUMERIC\n\n@DATA\n1.1, 2\n");
-        assert_eq!(to_string(&d_tuple).unwrap(), "@RELATION unnamed_data\n\n@ATTRIBUTE col1 NUMERIC\n@ATTRIBUTE col2 NUMERIC\n\n@DATA\n1.1, 2\n");
-        assert_eq!(to_string(&d_array).unwrap(), "@RELATION unnamed_data\n\n@ATTRIBUTE col1 NUMERIC\n@ATTRIBUTE col2 NUMERIC\n\n@DATA\n1.1, 2\n");
+        assert_eq!(
+            to_string(&d_struct).unwrap(),
+            "@RELATION unnamed_data\n\n@ATTRIBUTE x REAL\n@ATTRIBUTE y INTEGER\n\n@DATA\n1.1, 2\n"
+        );
+        assert_eq!(to_string(&d_tuple).unwrap(), "@RELATION unnamed_data\n\n@ATTRIBUTE col1 REAL\n@ATTRIBUTE col2 INTEGER\n\n@DATA\n1.1, 2\n");
+        assert_eq!(to_string(&d_array).unwrap(), "@RELATION unnamed_data\n\n@ATTRIBUTE col1 REAL\n@ATTRIBUTE col2 REAL\n\n@DATA\n1.1, 2\n");
+    }
+
+    #[test]
+    fn read_schema() {
+        let input = "% sensor data
+@RELATION sensors
+@ATTRIBUTE id NUMERIC
+@ATTRIBUTE 'sensor name' STRING
+@ATTRIBUTE status {ok, failed}
+@ATTRIBUTE time DATE \"yyyy-MM-dd\"
+@ATTRIBUTE readings RELATIONAL
+  @ATTRIBUTE value NUMERIC
+@END readings
+@DATA
+1, 'a', ok, 2018-07-10, '1\\n2'
+";
+
+        let header = read_header(input).unwrap();
+        assert_eq!(header.name, "sensors");
+        assert_eq!(header.attrs.len(), 5);
+        assert_eq!(header.attrs[1].dtype, DType::String);
+        assert_eq!(
+            header.attr("status").map(|attr| &attr.dtype),
+            Some(&DType::Nominal(vec!["ok".to_owned(), "failed".to_owned()]))
+        );
+        assert_eq!(
+            header.attrs[4].dtype,
+            DType::Relational(vec![Attribute {
+                name: "value".to_owned(),
+                dtype: DType::Numeric,
+            }])
+        );
+        assert_eq!(header.attr("missing"), None);
+
+        assert_eq!(
+            read_header("@RELATION empty\n@DATA"),
+            Ok(Header {
+                name: "empty".to_owned(),
+                attrs: vec![],
+                relation_comments: vec![],
+                comments: vec![],
+            })
+        );
+        assert!(read_header("@RELATION broken\n@ATTRIBUTE x NUMERIC\n@FOO\n").is_err());
+
+        let de = Deserializer::from_str(input).unwrap();
+        assert_eq!(de.header(), &header);
+
+        let dset = dynamic::DataSet::from_str(input).unwrap();
+        assert_eq!(dset.schema(), header);
+    }
+
+    #[test]
+    fn roundtrip_comments() {
+        let input = "% 1. Title: Iris Plants Database
+%
+% 2. Sources: R.A. Fisher
+@RELATION iris
+% after relation
+
+@ATTRIBUTE sepallength NUMERIC  % not kept either
+% the class attribute
+@ATTRIBUTE class {Iris-setosa, Iris-versicolor}
+% end of header
+@DATA
+% first batch
+5.1, Iris-setosa
+4.9, Iris-setosa  % not a full-line comment
+% second batch
+7, Iris-versicolor
+% end of data
+";
+
+        let header = read_header(input).unwrap();
+        assert_eq!(
+            header.relation_comments,
+            vec![
+                " 1. Title: Iris Plants Database".to_owned(),
+                "".to_owned(),
+                " 2. Sources: R.A. Fisher".to_owned(),
+            ]
+        );
+        assert_eq!(
+            header.comments,
+            vec![
+                (0, " after relation".to_owned()),
+                (1, " the class attribute".to_owned()),
+                (2, " end of header".to_owned()),
+            ]
+        );
+
+        let dset = dynamic::DataSet::from_str(input).unwrap();
+        assert_eq!(dset.relation_comments(), &header.relation_comments[..]);
+        assert_eq!(dset.comments(), &header.comments[..]);
+        assert_eq!(
+            dset.data_comments(),
+            &[
+                (0, " first batch".to_owned()),
+                (2, " second batch".to_owned()),
+                (3, " end of data".to_owned()),
+            ]
+        );
+
+        let mut writer = ArffWriter::new(Vec::new(), &dset.schema()).unwrap();
+        writer.write_dataset(&dset).unwrap();
+        let output = String::from_utf8(writer.into_inner()).unwrap();
+        assert_eq!(
+            output,
+            "% 1. Title: Iris Plants Database
+%
+% 2. Sources: R.A. Fisher
+@RELATION iris
+
+% after relation
+@ATTRIBUTE sepallength NUMERIC
+% the class attribute
+@ATTRIBUTE class {Iris-setosa, Iris-versicolor}
+
+% end of header
+@DATA
+% first batch
+5.1, Iris-setosa
+4.9, Iris-setosa
+% second batch
+7, Iris-versicolor
+% end of data
+"
+        );
+        assert_eq!(dynamic::DataSet::from_str(&output).unwrap(), dset);
     }
 }
@@ -6,9 +6,14 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+use std::borrow::Cow;
+use std::fmt;
+use std::io::{self, BufRead};
 use std::str;
+use std::vec;
 use std::{f64, i16, i32, i64, u16, u32, u64, u8};
 
+use super::date::DateFormat;
 use super::error::{Error, Result};
 
 pub const I16_MIN: i64 = i16::MIN as i64;
@@ -25,6 +30,7 @@ pub const I32_MINABS: u64 = I32_MAX as u64 + 1;
 pub const I64_MAX: u64 = i64::MAX as u64;
 pub const I64_MINABS: u64 = I64_MAX + 1;
 
+#[derive(Debug, PartialEq)]
 pub enum DynamicValue {
     U8(u8),
     U16(u16),
@@ -38,26 +44,118 @@ pub enum DynamicValue {
     String(String),
 }
 
+/// The declared type of an attribute
+///
+/// More types may be added in the future, so matches on it need a wildcard arm.
 #[derive(Debug, Clone, Eq, PartialEq)]
+#[non_exhaustive]
 pub enum DType {
+    /// `NUMERIC`
     Numeric,
+    /// `INTEGER`: numeric, with whole numbers only
+    Integer,
+    /// `REAL`: numeric, with real numbers
+    Real,
+    /// `STRING`
     String,
-    //Date(String),
+    /// `DATE`, with the declared (or default) format
+    Date(DateFormat),
+    /// `{...}`, with the categories in declaration order
     Nominal(Vec<String>),
+    /// `RELATIONAL`, with the attributes of the nested rows
+    Relational(Vec<Attribute>),
 }
 
+/// An attribute (column) declared in the header
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct Attribute {
-    pub name: String,
-    pub dtype: DType,
+    pub(crate) name: String,
+    pub(crate) dtype: DType,
 }
 
-#[derive(Debug, PartialEq)]
+impl Attribute {
+    /// Create an attribute declaration.
+    pub fn new<S: Into<String>>(name: S, dtype: DType) -> Self {
+        Attribute {
+            name: name.into(),
+            dtype,
+        }
+    }
+
+    /// the name of the attribute
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// the declared type of the attribute
+    pub fn dtype(&self) -> &DType {
+        &self.dtype
+    }
+}
+
+/// The header of an ARFF file: the relation name and the attributes
+#[derive(Debug, Clone, Eq, PartialEq)]
 pub struct Header {
-    pub name: String,
-    pub attrs: Vec<Attribute>,
+    pub(crate) name: String,
+    pub(crate) attrs: Vec<Attribute>,
+    /// full-line comments without the leading `%` before the relation declaration
+    pub(crate) relation_comments: Vec<String>,
+    /// full-line comments without the leading `%`, with the index of the attribute that follows
+    pub(crate) comments: Vec<(usize, String)>,
 }
 
+impl Header {
+    /// Create a header without comments.
+    pub fn new<S: Into<String>>(name: S, attrs: Vec<Attribute>) -> Self {
+        Header {
+            name: name.into(),
+            attrs,
+            relation_comments: Vec::new(),
+            comments: Vec::new(),
+        }
+    }
+
+    /// the name of the relation
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// the declared attributes, in the order of the data columns
+    pub fn attrs(&self) -> &[Attribute] {
+        &self.attrs
+    }
+
+    /// The full-line comments before `@RELATION`, without the leading `%`
+    pub fn relation_comments(&self) -> &[String] {
+        &self.relation_comments
+    }
+
+    /// Replace the comments before `@RELATION`, e.g. to note where the data comes from
+    pub fn set_relation_comments(&mut self, comments: Vec<String>) {
+        self.relation_comments = comments;
+    }
+
+    /// The full-line comments of the header after `@RELATION`, without the leading `%`, each
+    /// with the index of the attribute that follows it
+    ///
+    /// Comments before `@DATA` have the index `attrs().len()`. Comments at the end of a
+    /// declaration and comments between the attributes of a relational attribute are dropped.
+    pub fn comments(&self) -> &[(usize, String)] {
+        &self.comments
+    }
+
+    /// Replace the comments of the header, e.g. to note how the data was derived
+    pub fn set_comments(&mut self, comments: Vec<(usize, String)>) {
+        self.comments = comments;
+    }
+
+    /// the declaration of the attribute with the given name, if any
+    pub fn attr(&self, name: &str) -> Option<&Attribute> {
+        self.attrs.iter().find(|attr| attr.name == name)
+    }
+}
+
+/// A position in the input text
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub struct TextPos {
     line: usize,
@@ -68,32 +166,184 @@ impl TextPos {
     pub fn new(line: usize, column: usize) -> Self {
         TextPos { line, column }
     }
+
+    /// line number, starting at 1
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// column number in bytes, starting at 1
+    pub fn column(&self) -> usize {
+        self.column
+    }
+}
+
+impl fmt::Display for TextPos {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
+/// The text a parser reads from
+enum Input<'a> {
+    /// the whole text and the bytes that have not been read yet
+    Borrowed(&'a str, str::Bytes<'a>),
+    Owned(vec::IntoIter<u8>),
+    Reader(ReaderInput<'a>),
+}
+
+impl<'a> Iterator for Input<'a> {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        match *self {
+            Input::Borrowed(_, ref mut bytes) => bytes.next(),
+            Input::Owned(ref mut bytes) => bytes.next(),
+            Input::Reader(ref mut reader) => reader.next(),
+        }
+    }
+}
+
+/// Incrementally read text from a `BufRead`, one buffer at a time
+///
+/// Read errors end the input. They are kept so the parser can report them instead of the
+/// error caused by the premature end of input.
+struct ReaderInput<'a> {
+    reader: Box<dyn BufRead + 'a>,
+    buffer: Vec<u8>,
+    pos: usize,
+    error: Option<io::Error>,
+}
+
+impl<'a> ReaderInput<'a> {
+    fn new(reader: Box<dyn BufRead + 'a>) -> Self {
+        ReaderInput {
+            reader,
+            buffer: Vec::new(),
+            pos: 0,
+            error: None,
+        }
+    }
+
+    /// replace the buffer with the next chunk of input; returns false at the end of input
+    fn refill(&mut self) -> bool {
+        if self.error.is_some() {
+            return false;
+        }
+
+        self.buffer.clear();
+        self.pos = 0;
+        loop {
+            match self.reader.fill_buf() {
+                Ok(data) => {
+                    self.buffer.extend_from_slice(data);
+                    break;
+                }
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    self.error = Some(e);
+                    return false;
+                }
+            }
+        }
+        self.reader.consume(self.buffer.len());
+        !self.buffer.is_empty()
+    }
+}
+
+impl<'a> Iterator for ReaderInput<'a> {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        if self.pos >= self.buffer.len() && !self.refill() {
+            return None;
+        }
+        self.pos += 1;
+        Some(self.buffer[self.pos - 1])
+    }
 }
 
 pub struct Parser<'a> {
-    input: str::Bytes<'a>,
+    input: Input<'a>,
     current_char: u8,
     pos: TextPos,
     buffer: Vec<u8>, // reusable scratch space
+    strict: bool,
 }
 
 impl<'a> Parser<'a> {
     pub fn new(input: &'a str) -> Self {
+        Parser::with_input(Input::Borrowed(input, input.bytes()))
+    }
+
+    /// create a parser that owns its input, e.g. the nested rows of a relational value
+    pub fn from_string(input: String) -> Self {
+        Parser::with_input(Input::Owned(input.into_bytes().into_iter()))
+    }
+
+    /// create a parser that reads its input incrementally
+    pub fn from_reader<R: BufRead + 'a>(reader: R) -> Self {
+        Parser::with_input(Input::Reader(ReaderInput::new(Box::new(reader))))
+    }
+
+    /// create a parser for a part of the input that starts at the beginning of line `line`
+    #[cfg(feature = "parallel")]
+    pub fn with_first_line(input: &'a str, line: usize) -> Self {
+        let mut p = Parser::new(input);
+        p.pos.line = line;
+        p
+    }
+
+    fn with_input(input: Input<'a>) -> Self {
         let mut p = Parser {
-            input: input.bytes(),
+            input,
             current_char: 0,
             pos: TextPos { line: 1, column: 0 },
             buffer: Vec::new(),
+            strict: false,
         };
         p.advance();
         p
     }
 
+    /// check values against the declared attribute types, e.g. reject `1.5` in an `INTEGER`
+    /// column
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    /// are values checked against the declared attribute types?
+    pub fn is_strict(&self) -> bool {
+        self.strict
+    }
+
     /// has the parser reached the end of input?
     pub fn is_eof(&self) -> bool {
         self.current_char == 0
     }
 
+    /// replace the result of parsing with the read error that ended the input early, if any
+    pub fn check_io<T>(&mut self, result: Result<T>) -> Result<T> {
+        if let Input::Reader(ref mut reader) = self.input {
+            if let Some(e) = reader.error.take() {
+                return Err(e.into());
+            }
+        }
+        result
+    }
+
+    /// the input that has not been parsed yet, starting with the current character
+    ///
+    /// Returns `None` unless the parser borrows its input and is at the start of a character.
+    #[cfg(feature = "parallel")]
+    pub fn remaining(&self) -> Option<&'a str> {
+        match self.input {
+            Input::Borrowed(text, _) if self.is_eof() => Some(&text[text.len()..]),
+            Input::Borrowed(text, ref bytes) => text.get(text.len() - bytes.len() - 1..),
+            _ => None,
+        }
+    }
+
     /// get current parser position in the input
     pub fn pos(&self) -> TextPos {
         self.pos
@@ -131,9 +381,40 @@ impl<'a> Parser<'a> {
         }
     }
 
-    /// set parser to next occurence of given character
+    /// like `skip_empty`, but collect the text of full-line comments
+    pub fn skip_empty_comments(&mut self, comments: &mut Vec<String>) {
+        loop {
+            match self.current_char {
+                b' ' => self.advance(),
+                b'\n' => self.assume_newline(),
+                b'%' => comments.push(self.parse_comment()),
+                _ => return,
+            }
+        }
+    }
+
+    /// parse a comment up to the end of the line and return its text without the leading `%`
+    fn parse_comment(&mut self) -> String {
+        self.advance();
+        let mut text = Vec::new();
+        while self.current_char != b'\n' && self.current_char != 0 {
+            text.push(self.current_char);
+            self.advance();
+        }
+        if text.last() == Some(&b'\r') {
+            text.pop();
+        }
+        String::from_utf8_lossy(&text).into_owned()
+    }
+
+    /// set parser to the end of the current line, e.g. to resume after an erroneous row
+    pub fn skip_line(&mut self) {
+        self.skip_until(b'\n');
+    }
+
+    /// set parser to next occurence of given character or end of input
     fn skip_until(&mut self, ch: u8) {
-        while self.current_char != ch {
+        while self.current_char != ch && self.current_char != 0 {
             self.advance();
         }
     }
@@ -189,15 +470,87 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// parse the value of a relational attribute and return a parser for its nested rows
+    ///
+    /// Weka writes the nested rows as a single quoted string, separating them with `\n`.
+    pub fn parse_nested_rows(&mut self) -> Result<Parser<'static>> {
+        let rows = self.parse_string()?;
+        let mut parser = Parser::from_string(rows);
+        parser.set_strict(self.strict);
+        Ok(parser)
+    }
+
+    /// parse a quoted or unquoted string, borrowing it from the input if possible
+    ///
+    /// Strings are borrowed if the parser reads from a `&str` and they contain no escapes.
+    pub fn parse_str(&mut self) -> Result<Cow<'a, str>> {
+        let (text, start) = match self.input {
+            Input::Borrowed(text, ref bytes) => (text, text.len() - bytes.len()),
+            _ => return self.parse_string().map(Cow::Owned),
+        };
+        // `start` is the offset of the character after the current one
+        let offset = |parser: &Self| match parser.input {
+            Input::Borrowed(_, ref bytes) if !parser.is_eof() => text.len() - bytes.len() - 1,
+            _ => text.len(),
+        };
+
+        match self.current_char {
+            b'\'' | b'\"' => {
+                let delimiter = self.current_char;
+                self.advance();
+                loop {
+                    match self.current_char {
+                        0 => return Err(Error::Eof),
+                        b'\\' => {
+                            let prefix = text.as_bytes()[start..offset(self)].to_vec();
+                            return self.parse_quoted_rest(delimiter, prefix).map(Cow::Owned);
+                        }
+                        ch if ch == delimiter => break,
+                        _ => self.advance(),
+                    }
+                }
+                let end = offset(self);
+                self.advance();
+                Ok(Cow::Borrowed(&text[start..end]))
+            }
+            _ => {
+                let start = offset(self);
+                loop {
+                    match self.current_char {
+                        0 | b' ' | b'\t' | b'\n' | b',' | b'}' => break,
+                        _ => self.advance(),
+                    }
+                }
+                Ok(Cow::Borrowed(&text[start..offset(self)]))
+            }
+        }
+    }
+
     /// parse a string with `'` or `"`  delimiting characters
+    ///
+    /// Backslash escapes are resolved: `\n`, `\t` and `\r` stand for the corresponding control
+    /// characters, and any other escaped character (e.g. `\'` or `\\`) stands for itself.
     fn parse_quoted_string(&mut self) -> Result<String> {
         let delimiter = self.current_char;
         self.advance();
+        self.parse_quoted_rest(delimiter, Vec::new())
+    }
 
-        let mut s = Vec::new();
+    /// parse the remainder of a quoted string, after the part in `s`
+    fn parse_quoted_rest(&mut self, delimiter: u8, mut s: Vec<u8>) -> Result<String> {
         loop {
             match self.current_char {
                 0 => return Err(Error::Eof),
+                b'\\' => {
+                    self.advance();
+                    match self.current_char {
+                        0 => return Err(Error::Eof),
+                        b'n' => s.push(b'\n'),
+                        b't' => s.push(b'\t'),
+                        b'r' => s.push(b'\r'),
+                        ch => s.push(ch),
+                    }
+                }
                 ch if ch == delimiter => break,
                 ch => s.push(ch),
             }
@@ -213,7 +566,7 @@ impl<'a> Parser<'a> {
         let mut s = Vec::new();
         loop {
             match self.current_char {
-                0 | b' ' | b'\t' | b'\n' | b',' => break,
+                0 | b' ' | b'\t' | b'\n' | b',' | b'}' => break,
                 ch => s.push(ch),
             }
             self.advance();
@@ -233,47 +586,191 @@ impl<'a> Parser<'a> {
         }
     }
 
-    /// parse name and dtype of an @ATTRIBUTE declaration
-    pub fn parse_attribute(&mut self) -> Result<Attribute> {
-        let name = self.parse_string()?;
+    /// make sure that nothing but whitespace or a comment follows a declaration on its line
+    fn parse_end_of_declaration(&mut self) -> Result<()> {
         self.skip_whitespace();
+        match self.current_char {
+            0 | b'\n' => Ok(()),
+            b'%' => {
+                self.skip_until(b'\n');
+                Ok(())
+            }
+            _ => Err(Error::Expected(self.pos, "end of line")),
+        }
+    }
 
-        let pos = self.pos;
-
+    /// parse a keyword of the header, like the type of an attribute
+    ///
+    /// Keywords extend to the next whitespace or comment.
+    fn parse_keyword(&mut self) -> Result<String> {
         let mut s = Vec::new();
         loop {
             match self.current_char {
-                b'%' | b'\n' => break,
+                0 | b'%' | b'@' | b'\'' | b'"' | b'{' => break,
+                ch if ch.is_ascii_whitespace() => break,
                 ch => s.push(ch),
             }
             self.advance();
         }
-        let mut s = String::from_utf8(s)?;
+        Ok(String::from_utf8(s)?)
+    }
 
-        if s.starts_with('{') && s.ends_with('}') {
-            let categories = s[1..s.len() - 1]
-                .split(',')
-                .map(|s| s.trim().to_owned())
-                .collect();
+    /// parse name and dtype of an @ATTRIBUTE declaration, up to the end of its line
+    pub fn parse_attribute(&mut self) -> Result<Attribute> {
+        let pos = self.pos;
+        let name = self.parse_string()?;
+        if name.is_empty() {
+            return Err(Error::Expected(pos, "attribute name"));
+        }
+        self.skip_whitespace();
+
+        if self.current_char == b'{' {
+            let categories = self.parse_nominal_categories()?;
+            self.parse_end_of_declaration()?;
             return Ok(Attribute {
                 name,
                 dtype: DType::Nominal(categories),
             });
         }
 
-        s.make_ascii_uppercase();
+        let pos = self.pos;
+        let keyword = self.parse_keyword()?;
+        let dtype = match keyword.to_ascii_uppercase().as_ref() {
+            "NUMERIC" => DType::Numeric,
+            "INTEGER" => DType::Integer,
+            "REAL" => DType::Real,
+            "STRING" => DType::String,
+            "DATE" => DType::Date(self.parse_date_format(pos, &keyword)?),
+            "RELATIONAL" => {
+                self.parse_end_of_declaration()?;
+                let attrs = self.parse_relational_attributes(&name)?;
+                return Ok(Attribute {
+                    name,
+                    dtype: DType::Relational(attrs),
+                });
+            }
+            "" => return Err(Error::Expected(pos, "attribute type")),
+            _ => return Err(Error::InvalidColumnType(pos, keyword)),
+        };
+        self.parse_end_of_declaration()?;
+
+        Ok(Attribute { name, dtype })
+    }
 
-        match &s[..4] {
-            "NUME" | "REAL" | "INTE" => Ok(Attribute {
-                name,
-                dtype: DType::Numeric,
-            }),
-            "STRI" => Ok(Attribute {
-                name,
-                dtype: DType::String,
-            }),
-            "DATE" => Err(Error::UnsupportedColumnType(pos, s)),
-            _ => Err(Error::InvalidColumnType(pos, s)),
+    /// parse the optional, possibly quoted format of a date attribute
+    fn parse_date_format(&mut self, pos: TextPos, keyword: &str) -> Result<DateFormat> {
+        self.skip_whitespace();
+        let (pattern, declaration) = match self.current_char {
+            0 | b'\n' | b'%' => return Ok(DateFormat::default()),
+            delimiter @ b'\'' | delimiter @ b'"' => {
+                let pattern = self.parse_quoted_string()?;
+                let delimiter = delimiter as char;
+                let declaration = format!("{} {}{}{}", keyword, delimiter, pattern, delimiter);
+                (pattern, declaration)
+            }
+            _ => {
+                let pattern = self.parse_keyword()?;
+                let declaration = format!("{} {}", keyword, pattern);
+                (pattern, declaration)
+            }
+        };
+        DateFormat::new(&pattern).map_err(|_| Error::UnsupportedColumnType(pos, declaration))
+    }
+
+    /// parse the list of categories of a nominal attribute: `{a, 'b c', ...}`
+    fn parse_nominal_categories(&mut self) -> Result<Vec<String>> {
+        self.consume(b'{')?;
+
+        let mut categories = Vec::new();
+        self.skip_whitespace();
+        if self.consume_optional(b'}') {
+            return Ok(categories);
+        }
+
+        loop {
+            self.skip_whitespace();
+            categories.push(self.parse_nominal_category()?);
+            self.skip_whitespace();
+            match self.current_char {
+                b',' => self.advance(),
+                b'}' => {
+                    self.advance();
+                    return Ok(categories);
+                }
+                _ => return Err(Error::Expected(self.pos, "`,` or `}`")),
+            }
+        }
+    }
+
+    /// parse a single category in the declaration of a nominal attribute
+    ///
+    /// Unquoted categories extend to the next `,` or `}` and may contain spaces.
+    fn parse_nominal_category(&mut self) -> Result<String> {
+        match self.current_char {
+            b'\'' | b'\"' => return self.parse_quoted_string(),
+            _ => {}
+        }
+
+        let pos = self.pos;
+        let mut s = Vec::new();
+        loop {
+            match self.current_char {
+                0 | b'\n' | b',' | b'}' => break,
+                ch => s.push(ch),
+            }
+            self.advance();
+        }
+
+        let category = String::from_utf8(s)?.trim_end().to_owned();
+        if category.is_empty() {
+            return Err(Error::Expected(pos, "nominal category"));
+        }
+        Ok(category)
+    }
+
+    /// parse the nested attributes of a relational attribute, up to and including `@END name`
+    fn parse_relational_attributes(&mut self, name: &str) -> Result<Vec<Attribute>> {
+        let mut attrs = Vec::new();
+
+        loop {
+            self.skip_to_declaration(&mut Vec::new())?;
+
+            let pos = self.pos;
+            let mut token = self.parse_unquoted_string()?;
+            token.make_ascii_uppercase();
+
+            match token.as_ref() {
+                "@ATTRIBUTE" => {
+                    self.skip_whitespace();
+                    attrs.push(self.parse_attribute()?);
+                }
+                "@END" => {
+                    self.skip_whitespace();
+                    let pos = self.pos;
+                    if self.parse_string()? != name {
+                        return Err(Error::Expected(pos, "name of the relational attribute"));
+                    }
+                    self.parse_end_of_declaration()?;
+                    return Ok(attrs);
+                }
+                _ => return Err(Error::Expected(pos, "`@ATTRIBUTE` or `@END`")),
+            }
+        }
+    }
+
+    /// set parser to the start of the next declaration, collecting full-line comments
+    ///
+    /// Only whitespace, empty lines and comments may come before and between declarations; the
+    /// caller reports anything else as an unexpected token.
+    fn skip_to_declaration(&mut self, comments: &mut Vec<String>) -> Result<()> {
+        loop {
+            match self.current_char {
+                b'\n' => self.consume_newline()?,
+                b'%' => comments.push(self.parse_comment()),
+                0 => return Err(Error::Eof),
+                ch if ch.is_ascii_whitespace() => self.advance(),
+                _ => return Ok(()),
+            }
         }
     }
 
@@ -281,40 +778,52 @@ impl<'a> Parser<'a> {
     pub fn parse_header(&mut self) -> Result<Header> {
         let mut name = String::from("unnamed_data");
         let mut attrs = Vec::new();
+        let mut relation_comments = Vec::new();
+        let mut comments = Vec::new();
 
         loop {
-            loop {
-                // we are pretty liberal in accepting anything before and between @-declarations
-                match self.current_char {
-                    b'@' => break,
-                    b'\n' => self.consume_newline()?,
-                    b'%' => self.skip_until(b'\n'),
-                    0 => return Err(Error::Eof),
-                    _ => self.advance(),
-                }
-            }
+            let mut text = Vec::new();
+            self.skip_to_declaration(&mut text)?;
 
             let pos = self.pos;
             let mut token = self.parse_unquoted_string()?;
             token.make_ascii_uppercase();
 
+            if token == "@RELATION" {
+                relation_comments.extend(text);
+            } else {
+                let index = attrs.len();
+                comments.extend(text.into_iter().map(|comment| (index, comment)));
+            }
+
             match token.as_ref() {
                 "@DATA" => {
-                    self.ignore_comment();
-                    self.consume_newline()?;
-                    return Ok(Header { name, attrs });
+                    self.parse_end_of_declaration()?;
+                    if !self.is_eof() {
+                        self.consume_newline()?;
+                    }
+                    return Ok(Header {
+                        name,
+                        attrs,
+                        relation_comments,
+                        comments,
+                    });
                 }
                 "@RELATION" => {
                     self.skip_whitespace();
                     name = self.parse_string()?;
-                    self.ignore_comment();
+                    self.parse_end_of_declaration()?;
                 }
                 "@ATTRIBUTE" => {
                     self.skip_whitespace();
                     attrs.push(self.parse_attribute()?);
-                    self.ignore_comment();
                 }
-                _ => return Err(Error::Expected(pos, "`@RELATION`, `@ATTRIBUTE`, or `@DATA")),
+                _ => {
+                    return Err(Error::Expected(
+                        pos,
+                        "`@RELATION`, `@ATTRIBUTE`, or `@DATA`",
+                    ))
+                }
             }
         }
     }
@@ -384,10 +893,73 @@ impl<'a> Parser<'a> {
         self.consume_optional(b'?')
     }
 
+    /// Check for the opening brace of a sparse row. This cannot fail.
+    pub fn parse_is_sparse(&mut self) -> bool {
+        self.consume_optional(b'{')
+    }
+
+    /// Parse the column index of the next value in a sparse row.
+    ///
+    /// Returns `None` and consumes the closing brace if there are no more values in the row.
+    pub fn parse_sparse_index(&mut self) -> Result<Option<usize>> {
+        self.skip_whitespace();
+        if self.consume_optional(b'}') {
+            return Ok(None);
+        }
+        let pos = self.pos;
+        let index = match self.parse_u64() {
+            Ok(index) => index as usize,
+            Err(Error::ExpectedUnsignedValue(_, _)) => {
+                return Err(Error::Expected(pos, "sparse column index or `}`"))
+            }
+            Err(e) => return Err(e),
+        };
+        match self.current_char {
+            b' ' | b'\t' => self.skip_whitespace(),
+            _ => return Err(Error::Expected(self.pos, "space after sparse column index")),
+        }
+        Ok(Some(index))
+    }
+
+    /// Parse the delimiter following a value in a sparse row: comma or closing brace
+    ///
+    /// The closing brace is not consumed.
+    pub fn parse_sparse_delimiter(&mut self) -> Result<()> {
+        self.skip_whitespace();
+        match self.current_char {
+            b',' => {
+                self.advance();
+                self.skip_whitespace();
+            }
+            b'}' => {}
+            _ => return Err(Error::Expected(self.pos, "`,` or `}`")),
+        }
+        Ok(())
+    }
+
+    /// Parse the optional instance weight at the end of a row: `, {0.75}`
+    pub fn parse_weight(&mut self) -> Result<Option<f64>> {
+        self.skip_whitespace();
+        if !self.consume_optional(b',') {
+            return Ok(None);
+        }
+        self.skip_whitespace();
+        if !self.consume_optional(b'{') {
+            return Err(Error::Expected(self.pos, "instance weight `{`"));
+        }
+        self.skip_whitespace();
+        let weight = self.parse_float()?;
+        self.skip_whitespace();
+        if !self.consume_optional(b'}') {
+            return Err(Error::Expected(self.pos, "`}`"));
+        }
+        Ok(Some(weight))
+    }
+
     /// Parse a boolean value
     pub fn parse_bool(&mut self) -> Result<bool> {
         let pos = self.pos;
-        let strval = self.parse_unquoted_string()?.to_ascii_uppercase();
+        let strval = self.parse_string()?.to_ascii_uppercase();
         match strval.as_ref() {
             "0" | "F" | "FALSE" | "N" | "NO" => Ok(false),
             "1" | "T" | "TRUE" | "Y" | "YES" => Ok(true),
@@ -399,25 +971,14 @@ impl<'a> Parser<'a> {
     pub fn parse_u64(&mut self) -> Result<u64> {
         let pos = self.pos();
 
-        let mut value = match self.current_char {
-            ch @ b'0'...b'9' => (ch as u8 - b'0') as u64,
-            b'+' => 0,
-            _ => return Err(Error::ExpectedUnsignedValue(pos)),
-        };
-
-        loop {
+        if self.current_char == b'+' {
             self.advance();
-            match self.current_char {
-                ch @ b'0'...b'9' => {
-                    value = value
-                        .checked_mul(10)
-                        .ok_or(Error::NumericOverflow(pos))?
-                        .checked_add((ch - b'0') as u64)
-                        .ok_or(Error::NumericOverflow(pos))?;
-                }
-                _ => return Ok(value),
-            }
+        } else if !self.current_char.is_ascii_digit() {
+            let found = self.parse_malformed_value(Vec::new());
+            return Err(Error::ExpectedUnsignedValue(pos, found));
         }
+
+        self.parse_digits().ok_or(Error::NumericOverflow(pos))
     }
 
     /// Parse a signed integer value
@@ -436,35 +997,69 @@ impl<'a> Parser<'a> {
             _ => false,
         };
 
-        match (negative, self.parse_u64()) {
-            (_, Err(Error::ExpectedUnsignedValue(_))) => Err(Error::ExpectedIntegerValue(pos)),
-            (_, Err(e)) => Err(e),
-            (true, Ok(I64_MINABS)) => Ok(i64::MIN),
-            (true, Ok(uval @ 0...I64_MAX)) => Ok(-(uval as i64)),
-            (false, Ok(uval @ 0...I64_MAX)) => Ok(uval as i64),
+        if !self.current_char.is_ascii_digit() {
+            let sign = if negative { vec![b'-'] } else { Vec::new() };
+            let found = self.parse_malformed_value(sign);
+            return Err(Error::ExpectedIntegerValue(pos, found));
+        }
+
+        match (negative, self.parse_digits()) {
+            (_, None) => Err(Error::NumericOverflow(pos)),
+            (true, Some(I64_MINABS)) => Ok(i64::MIN),
+            (true, Some(uval @ 0...I64_MAX)) => Ok(-(uval as i64)),
+            (false, Some(uval @ 0...I64_MAX)) => Ok(uval as i64),
             _ => Err(Error::NumericRange(pos, i64::MIN, i64::MAX)),
         }
     }
 
+    /// parse a sequence of decimal digits, returning `None` if the value does not fit into `u64`
+    fn parse_digits(&mut self) -> Option<u64> {
+        let mut value = 0u64;
+        while self.current_char.is_ascii_digit() {
+            let digit = (self.current_char - b'0') as u64;
+            value = value.checked_mul(10)?.checked_add(digit)?;
+            self.advance();
+        }
+        Some(value)
+    }
+
     /// Parse a floating point value
     pub fn parse_float(&mut self) -> Result<f64> {
         let pos = self.pos();
 
-        let mut s = Vec::new();
+        self.buffer.clear();
         loop {
             match self.current_char {
                 ch @ b'+' | ch @ b'-' | ch @ b'.' | ch @ b'e' | ch @ b'E' | ch @ b'0'...b'9' => {
-                    s.push(ch)
+                    self.buffer.push(ch)
                 }
                 _ => break,
             }
             self.advance();
         }
 
-        match String::from_utf8(s).unwrap().parse() {
-            Ok(v) => Ok(v),
-            Err(_) => Err(Error::ExpectedFloatValue(pos)),
+        match parse_f64(&self.buffer) {
+            Some(v) => Ok(v),
+            None => {
+                let s = self.buffer.clone();
+                Err(Error::ExpectedFloatValue(
+                    pos,
+                    self.parse_malformed_value(s),
+                ))
+            }
+        }
+    }
+
+    /// consume the rest of a value that failed to parse and return all of it for error messages
+    fn parse_malformed_value(&mut self, mut value: Vec<u8>) -> String {
+        loop {
+            match self.current_char {
+                0 | b'\n' | b',' | b'\t' | b' ' | b'}' => break,
+                ch => value.push(ch),
+            }
+            self.advance();
         }
+        String::from_utf8_lossy(&value).into_owned()
     }
 
     /// Try to parse value in most compact representation.
@@ -502,32 +1097,28 @@ impl<'a> Parser<'a> {
             }
             _ => false,
         };
+        let digits = self.buffer.len();
 
         let mut value = 0u64;
+        let mut overflow = false;
         loop {
             match self.current_char {
                 ch @ b'0'...b'9' => {
-                    value = value
+                    match value
                         .checked_mul(10)
-                        .ok_or(Error::NumericOverflow(pos))?
-                        .checked_add((ch - b'0') as u64)
-                        .ok_or(Error::NumericOverflow(pos))?;
-                }
-                0 | b' ' | b'\t' | b'\n' | b',' => match (negative, value) {
-                    (false, 0...255) => return Ok(Some(DynamicValue::U8(value as u8))),
-                    (true, 0...128) => return Ok(Some(DynamicValue::I8((-(value as i64)) as i8))),
-                    (false, 0...U16_MAX) => return Ok(Some(DynamicValue::U16(value as u16))),
-                    (true, 0...I16_MINABS) => {
-                        return Ok(Some(DynamicValue::I16((-(value as i64)) as i16)))
+                        .and_then(|v| v.checked_add((ch - b'0') as u64))
+                    {
+                        Some(v) => value = v,
+                        // too large for an integer, but it may still be a valid float
+                        None => {
+                            overflow = true;
+                            break;
+                        }
                     }
-                    (false, 0...U32_MAX) => return Ok(Some(DynamicValue::U32(value as u32))),
-                    (true, 0...I32_MINABS) => {
-                        return Ok(Some(DynamicValue::I32((-(value as i64)) as i32)))
-                    }
-                    (false, _) => return Ok(Some(DynamicValue::U64(value))),
-                    (true, I64_MINABS) => return Ok(Some(DynamicValue::I64(i64::MIN))),
-                    (true, 0...I64_MINABS) => return Ok(Some(DynamicValue::I64(-(value as i64)))),
-                    _ => break,
+                }
+                0 | b' ' | b'\t' | b'\n' | b',' | b'}' => match integer_value(negative, value) {
+                    Some(value) => return Ok(Some(value)),
+                    None => break,
                 },
                 _ => break,
             }
@@ -538,7 +1129,7 @@ impl<'a> Parser<'a> {
         // not an integer => collect remaining characters
         loop {
             match self.current_char {
-                0 | b' ' | b'\t' | b'\n' | b',' => break,
+                0 | b' ' | b'\t' | b'\n' | b',' | b'}' => break,
                 _ => {
                     self.buffer.push(self.current_char);
                     self.advance();
@@ -546,16 +1137,41 @@ impl<'a> Parser<'a> {
             }
         }
 
-        let s = String::from_utf8(self.buffer.drain(..).collect()).unwrap();
+        if overflow && self.buffer[digits..].iter().all(u8::is_ascii_digit) {
+            return Err(Error::NumericOverflow(pos));
+        }
 
         // either float or string
-        match s.parse::<f64>() {
-            Ok(value) => Ok(Some(DynamicValue::F64(value))),
-            Err(_) => Ok(Some(DynamicValue::String(s))),
+        match parse_f64(&self.buffer) {
+            Some(value) => Ok(Some(DynamicValue::F64(value))),
+            None => Ok(Some(DynamicValue::String(String::from_utf8(
+                self.buffer.clone(),
+            )?))),
         }
     }
 }
 
+/// parse a floating point number from the text of a value, without allocating
+fn parse_f64(text: &[u8]) -> Option<f64> {
+    str::from_utf8(text).ok()?.parse().ok()
+}
+
+/// the smallest integer type that holds the number with the given sign and absolute value
+pub(crate) fn integer_value(negative: bool, value: u64) -> Option<DynamicValue> {
+    match (negative, value) {
+        (false, 0...255) => Some(DynamicValue::U8(value as u8)),
+        (true, 0...128) => Some(DynamicValue::I8((-(value as i64)) as i8)),
+        (false, 0...U16_MAX) => Some(DynamicValue::U16(value as u16)),
+        (true, 0...I16_MINABS) => Some(DynamicValue::I16((-(value as i64)) as i16)),
+        (false, 0...U32_MAX) => Some(DynamicValue::U32(value as u32)),
+        (true, 0...I32_MINABS) => Some(DynamicValue::I32((-(value as i64)) as i32)),
+        (false, _) => Some(DynamicValue::U64(value)),
+        (true, I64_MINABS) => Some(DynamicValue::I64(i64::MIN)),
+        (true, 0...I64_MINABS) => Some(DynamicValue::I64(-(value as i64))),
+        _ => None,
+    }
+}
+
 macro_rules! impl_parse_primitive_unsigned {
     ($name:ident, $typ:ident, $min:expr, $max:expr) => {
         impl<'a> Parser<'a> {
@@ -626,14 +1242,266 @@ mod tests {
                 attrs: vec![
                     Attribute {
                         name: "pixel1".to_string(),
-                        dtype: DType::Numeric
+                        dtype: DType::Real
                     },
                     Attribute {
                         name: "pixel2".to_string(),
-                        dtype: DType::Numeric
+                        dtype: DType::Real
                     }
-                ]
+                ],
+                relation_comments: vec![],
+                comments: vec![],
             })
         )
     }
+
+    #[test]
+    fn date_attributes() {
+        let mut parser = Parser::new(
+            "
+@ATTRIBUTE a DATE
+@ATTRIBUTE b date \"yyyy-MM-dd HH:mm:ss\"  % comment
+@ATTRIBUTE c Date 'HH:mm'
+@DATA
+",
+        );
+        let header = parser.parse_header().unwrap();
+        let formats: Vec<_> = header
+            .attrs
+            .iter()
+            .map(|attr| match attr.dtype {
+                DType::Date(ref format) => format.pattern().to_owned(),
+                _ => panic!("expected date attribute"),
+            })
+            .collect();
+        assert_eq!(
+            formats,
+            vec!["yyyy-MM-dd'T'HH:mm:ss", "yyyy-MM-dd HH:mm:ss", "HH:mm"]
+        );
+
+        let mut parser = Parser::new("@ATTRIBUTE a DATE 'yyyy-ww'\n@DATA\n");
+        assert_eq!(
+            parser.parse_header(),
+            Err(Error::UnsupportedColumnType(
+                TextPos::new(1, 14),
+                "DATE 'yyyy-ww'".to_owned()
+            ))
+        );
+    }
+
+    #[test]
+    fn relational_attributes() {
+        let mut parser = Parser::new(
+            "
+@RELATION musk
+@ATTRIBUTE id {a, b}
+@ATTRIBUTE bag RELATIONAL
+  @ATTRIBUTE f1 NUMERIC
+  @ATTRIBUTE f2 NUMERIC
+@END bag
+@ATTRIBUTE class {0, 1}
+@DATA
+",
+        );
+        assert_eq!(
+            parser.parse_header(),
+            Ok(Header {
+                name: "musk".to_string(),
+                attrs: vec![
+                    Attribute {
+                        name: "id".to_string(),
+                        dtype: DType::Nominal(vec!["a".to_string(), "b".to_string()])
+                    },
+                    Attribute {
+                        name: "bag".to_string(),
+                        dtype: DType::Relational(vec![
+                            Attribute {
+                                name: "f1".to_string(),
+                                dtype: DType::Numeric
+                            },
+                            Attribute {
+                                name: "f2".to_string(),
+                                dtype: DType::Numeric
+                            },
+                        ])
+                    },
+                    Attribute {
+                        name: "class".to_string(),
+                        dtype: DType::Nominal(vec!["0".to_string(), "1".to_string()])
+                    },
+                ],
+                relation_comments: vec![],
+                comments: vec![],
+            })
+        );
+
+        let mut parser =
+            Parser::new("@ATTRIBUTE bag RELATIONAL\n@ATTRIBUTE f1 NUMERIC\n@END other\n@DATA\n");
+        assert_eq!(
+            parser.parse_header(),
+            Err(Error::Expected(
+                TextPos::new(3, 6),
+                "name of the relational attribute"
+            ))
+        );
+    }
+
+    #[test]
+    fn attribute_types() {
+        let parse = |input: &str| Parser::new(input).parse_attribute().map(|attr| attr.dtype);
+
+        assert_eq!(parse("x numeric"), Ok(DType::Numeric));
+        assert_eq!(parse("x Integer\n"), Ok(DType::Integer));
+        assert_eq!(parse("x REAL\t% comment\n"), Ok(DType::Real));
+        assert_eq!(parse("'x y' string\r\n"), Ok(DType::String));
+        assert_eq!(
+            parse("x {a, b}% comment"),
+            Ok(DType::Nominal(vec!["a".to_owned(), "b".to_owned()]))
+        );
+
+        assert_eq!(
+            parse("x int\n"),
+            Err(Error::InvalidColumnType(
+                TextPos::new(1, 3),
+                "int".to_owned()
+            ))
+        );
+        assert_eq!(
+            parse("x NUMERICALLY\n"),
+            Err(Error::InvalidColumnType(
+                TextPos::new(1, 3),
+                "NUMERICALLY".to_owned()
+            ))
+        );
+        assert_eq!(
+            parse("x STRING-like\n"),
+            Err(Error::InvalidColumnType(
+                TextPos::new(1, 3),
+                "STRING-like".to_owned()
+            ))
+        );
+        assert_eq!(
+            parse("x\n"),
+            Err(Error::Expected(TextPos::new(1, 2), "attribute type"))
+        );
+        assert_eq!(
+            parse("'' NUMERIC\n"),
+            Err(Error::Expected(TextPos::new(1, 1), "attribute name"))
+        );
+        assert_eq!(
+            parse("x NUMERIC 1\n"),
+            Err(Error::Expected(TextPos::new(1, 11), "end of line"))
+        );
+        assert_eq!(
+            parse("x {a, b} c\n"),
+            Err(Error::Expected(TextPos::new(1, 10), "end of line"))
+        );
+        assert_eq!(
+            parse("x DATE 'yyyy' HH\n"),
+            Err(Error::Expected(TextPos::new(1, 15), "end of line"))
+        );
+    }
+
+    #[test]
+    fn escaped_strings() {
+        let mut parser = Parser::new(r#"'it\'s' "say \"hi\"" 'a\nb\tc\\d\%e' 'x"y'"#);
+        assert_eq!(parser.parse_string(), Ok("it's".into()));
+        parser.skip_spaces();
+        assert_eq!(parser.parse_string(), Ok("say \"hi\"".into()));
+        parser.skip_spaces();
+        assert_eq!(parser.parse_string(), Ok("a\nb\tc\\d%e".into()));
+        parser.skip_spaces();
+        assert_eq!(parser.parse_string(), Ok("x\"y".into()));
+        assert!(parser.is_eof());
+
+        let mut parser = Parser::new(r"'abc\");
+        assert_eq!(parser.parse_string(), Err(Error::Eof));
+    }
+
+    #[test]
+    fn quoted_nominal_categories() {
+        let mut parser =
+            Parser::new(r#"city {'New York', 'Washington, D.C.', "it\'s",  plain words , x}"#);
+        assert_eq!(
+            parser.parse_attribute(),
+            Ok(Attribute {
+                name: "city".to_owned(),
+                dtype: DType::Nominal(vec![
+                    "New York".to_owned(),
+                    "Washington, D.C.".to_owned(),
+                    "it's".to_owned(),
+                    "plain words".to_owned(),
+                    "x".to_owned(),
+                ]),
+            })
+        );
+
+        let mut parser = Parser::new("empty { }");
+        assert_eq!(
+            parser.parse_attribute(),
+            Ok(Attribute {
+                name: "empty".to_owned(),
+                dtype: DType::Nominal(vec![]),
+            })
+        );
+
+        let mut parser = Parser::new("bad {a, , b}");
+        assert_eq!(
+            parser.parse_attribute(),
+            Err(Error::Expected(TextPos::new(1, 9), "nominal category"))
+        );
+
+        let mut parser = Parser::new("bad {'a' b}");
+        assert_eq!(
+            parser.parse_attribute(),
+            Err(Error::Expected(TextPos::new(1, 10), "`,` or `}`"))
+        );
+    }
+
+    #[test]
+    fn numbers() {
+        let mut parser = Parser::new("-12,+7,1.5e3,-.25,123456789012345678901.5,NaN,1.2.3,x1");
+        assert_eq!(parser.parse_i64(), Ok(-12));
+        parser.parse_column_delimiter().unwrap();
+        assert_eq!(parser.parse_u64(), Ok(7));
+        parser.parse_column_delimiter().unwrap();
+        assert_eq!(parser.parse_float(), Ok(1500.0));
+        parser.parse_column_delimiter().unwrap();
+        assert_eq!(parser.parse_dynamic(), Ok(Some(DynamicValue::F64(-0.25))));
+        parser.parse_column_delimiter().unwrap();
+        assert_eq!(
+            parser.parse_dynamic(),
+            Ok(Some(DynamicValue::F64(123456789012345678901.5)))
+        );
+        parser.parse_column_delimiter().unwrap();
+        match parser.parse_dynamic() {
+            Ok(Some(DynamicValue::F64(v))) => assert!(v.is_nan()),
+            v => panic!("expected NaN, got {:?}", v),
+        }
+        parser.parse_column_delimiter().unwrap();
+        assert_eq!(
+            parser.parse_float(),
+            Err(Error::ExpectedFloatValue(
+                TextPos::new(1, 47),
+                "1.2.3".to_owned()
+            ))
+        );
+        parser.parse_column_delimiter().unwrap();
+        assert_eq!(
+            parser.parse_i64(),
+            Err(Error::ExpectedIntegerValue(
+                TextPos::new(1, 53),
+                "x1".to_owned()
+            ))
+        );
+
+        // integers that do not fit into 64 bits are an error, not a float
+        let mut parser = Parser::new("1, 18446744073709551616");
+        parser.parse_dynamic().unwrap();
+        parser.parse_column_delimiter().unwrap();
+        assert_eq!(
+            parser.parse_dynamic(),
+            Err(Error::NumericOverflow(TextPos::new(1, 4)))
+        );
+    }
 }
@@ -9,74 +9,208 @@
 //! Serialize a Rust data structure to ARFF formatted text.
 
 use std::borrow::Cow;
-use std::collections::BTreeSet;
+use std::io::{self, BufReader, Read, Seek, SeekFrom, Write};
+use std::iter::Peekable;
 
 use serde::ser::{self, Serialize};
 
+use super::date::{DateFormat, DATE_TOKEN};
+use super::dynamic::DataSet;
 use super::error::{Error, Result};
+use super::parser::{self, Parser};
+use super::weighted::{Weighted, DEFAULT_WEIGHT, WEIGHTED_TOKEN};
 
 #[derive(Debug)]
 struct Header {
-    name: &'static str,
+    name: Cow<'static, str>,
     attr_names: Vec<Cow<'static, str>>,
     attr_types: Vec<DType>,
+    /// comments without the leading `%`, written before the relation
+    relation_comments: Vec<String>,
+    /// comments without the leading `%`, each written before the attribute with the given index
+    comments: Vec<(usize, String)>,
+    /// a fixed header was declared up front; rows are checked against it instead of extending it
+    fixed: bool,
 }
 
 impl Header {
     fn new() -> Self {
         Header {
-            name: "unnamed_data",
+            name: "unnamed_data".into(),
             attr_names: Vec::new(),
             attr_types: Vec::new(),
+            relation_comments: Vec::new(),
+            comments: Vec::new(),
+            fixed: false,
         }
     }
 
+    /// fixed header from a declared schema
+    fn from_schema(schema: &parser::Header) -> Result<Self> {
+        let mut attr_names = Vec::new();
+        let mut attr_types = Vec::new();
+
+        for attr in &schema.attrs {
+            let dtype = match attr.dtype {
+                parser::DType::Numeric => DType::Numeric,
+                parser::DType::Integer => DType::Integer,
+                parser::DType::Real => DType::Real,
+                parser::DType::String => DType::String,
+                parser::DType::Date(ref format) => DType::Date(format.clone()),
+                parser::DType::Nominal(ref categories) => {
+                    DType::Nominal(categories.iter().map(|c| c.clone().into()).collect())
+                }
+                parser::DType::Relational(_) => {
+                    return Err(Error::RelationalAttribute(attr.name.clone()))
+                }
+            };
+            attr_names.push(attr.name.clone().into());
+            attr_types.push(dtype);
+        }
+
+        Ok(Header {
+            name: schema.name.clone().into(),
+            attr_names,
+            attr_types,
+            relation_comments: schema.relation_comments.clone(),
+            comments: schema.comments.clone(),
+            fixed: true,
+        })
+    }
+
     fn to_string(&self) -> String {
-        let mut s = format!("@RELATION {}\n\n", self.name);
+        let mut s = String::new();
+        let mut comments = self.comments.iter().peekable();
 
-        for (aname, atype) in self.attr_names.iter().zip(&self.attr_types) {
-            s += &format!("@ATTRIBUTE {} {}\n", aname, atype.to_string());
+        for comment in &self.relation_comments {
+            s += &format!("%{}\n", comment);
+        }
+        s += &format!("@RELATION {}\n\n", quote_if_needed(&self.name));
+
+        for (i, (aname, atype)) in self.attr_names.iter().zip(&self.attr_types).enumerate() {
+            write_comments(&mut s, &mut comments, i);
+            s += &format!(
+                "@ATTRIBUTE {} {}\n",
+                quote_if_needed(aname),
+                atype.to_string()
+            );
         }
 
-        s + "\n@DATA\n"
+        s += "\n";
+        write_comments(&mut s, &mut comments, usize::MAX);
+        s + "@DATA\n"
+    }
+}
+
+/// write the comments up to the given index of the attribute or row that follows
+fn write_comments<'a, I>(s: &mut String, comments: &mut Peekable<I>, index: usize)
+where
+    I: Iterator<Item = &'a (usize, String)>,
+{
+    while let Some(&&(i, ref comment)) = comments.peek() {
+        if i > index {
+            break;
+        }
+        *s += &format!("%{}\n", comment);
+        comments.next();
     }
 }
 
 #[derive(Debug)]
 enum DType {
     Numeric,
-    Nominal(BTreeSet<&'static str>),
+    /// inferred from integer types
+    Integer,
+    /// inferred from floating point types
+    Real,
+    /// categories in declaration order; inferred categories are kept sorted
+    Nominal(Vec<Cow<'static, str>>),
     String,
-    //Date(String),
+    Date(DateFormat),
 }
 
 impl DType {
     fn to_string(&self) -> String {
         match *self {
             DType::Numeric => "NUMERIC".to_owned(),
+            DType::Integer => "INTEGER".to_owned(),
+            DType::Real => "REAL".to_owned(),
             DType::Nominal(ref names) => {
                 let mut s = "{".to_owned();
                 for (i, n) in names.iter().enumerate() {
                     if i > 0 {
                         s += ", ";
                     }
-                    s += n;
+                    s += &quote_if_needed(n);
                 }
                 s += "}";
                 s
             }
             DType::String => "STRING".to_owned(),
-            //DType::Date(_) => unimplemented!(),
+            DType::Date(ref format) => format!("DATE \"{}\"", format.pattern()),
+        }
+    }
+}
+
+/// Escape special characters with a backslash, the way Weka does.
+pub(crate) fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for ch in s.chars() {
+        match ch {
+            '\\' | '\'' | '"' | '%' => {
+                escaped.push('\\');
+                escaped.push(ch);
+            }
+            '\n' => escaped += "\\n",
+            '\t' => escaped += "\\t",
+            '\r' => escaped += "\\r",
+            _ => escaped.push(ch),
         }
     }
+    escaped
+}
+
+/// Enclose a string in single quotes, escaping special characters.
+pub(crate) fn quote(s: &str) -> String {
+    format!("'{}'", escape(s))
+}
+
+/// Quote a string only if it can't be read back unquoted.
+pub(crate) fn quote_if_needed(s: &str) -> String {
+    const SPECIAL: &[char] = &[' ', '\t', '\n', '\r', ',', '{', '}', '%', '\'', '"', '\\'];
+    if s.is_empty() || s == "?" || s.contains(SPECIAL) {
+        quote(s)
+    } else {
+        s.to_owned()
+    }
 }
 
 /// Serialize an instance of type `T` into an ARFF formatted string.
 pub fn to_string<T>(value: &T) -> Result<String>
+where
+    T: Serialize,
+{
+    write_string(value, false)
+}
+
+/// Serialize an instance of type `T` into an ARFF formatted string with sparse data rows.
+///
+/// Rows are written as `{index value, ...}`, leaving out zero-valued numeric and date
+/// columns and `false` booleans. Strings, nominal values and missing values are always
+/// written explicitly.
+pub fn to_string_sparse<T>(value: &T) -> Result<String>
+where
+    T: Serialize,
+{
+    write_string(value, true)
+}
+
+fn write_string<T>(value: &T, sparse: bool) -> Result<String>
 where
     T: Serialize,
 {
     let mut serializer = Serializer::new();
+    serializer.sparse = sparse;
     value.serialize(&mut serializer)?;
 
     let header = serializer.header.to_string();
@@ -84,24 +218,240 @@ where
     Ok(header + &serializer.output)
 }
 
+/// Serialize an instance of type `T` as ARFF formatted text into a writer.
+///
+/// The header can only be written once all rows are known, so the data is serialized twice:
+/// the first pass determines the header and discards the rows, the second pass writes the
+/// header followed by the rows. Only one row at a time is held in memory.
+pub fn to_writer<W, T>(writer: W, value: &T) -> Result<()>
+where
+    W: Write,
+    T: Serialize,
+{
+    write_two_pass(writer, value, false)
+}
+
+/// Serialize an instance of type `T` as ARFF formatted text with sparse data rows into a writer.
+///
+/// See `to_writer` and `to_string_sparse`.
+pub fn to_writer_sparse<W, T>(writer: W, value: &T) -> Result<()>
+where
+    W: Write,
+    T: Serialize,
+{
+    write_two_pass(writer, value, true)
+}
+
+fn write_two_pass<W, T>(mut writer: W, value: &T, sparse: bool) -> Result<()>
+where
+    W: Write,
+    T: Serialize,
+{
+    let mut sink = io::sink();
+    let mut serializer = Serializer::new();
+    serializer.sparse = sparse;
+    serializer.writer = Some(&mut sink);
+    value.serialize(&mut serializer)?;
+
+    writer.write_all(serializer.header.to_string().as_bytes())?;
+
+    serializer.writer = Some(&mut writer);
+    serializer.current_row = 0;
+    value.serialize(&mut serializer)?;
+
+    writer.flush()?;
+    Ok(())
+}
+
 /// Serialize a Rust data structure into an ARFF formatted string.
-pub struct Serializer {
+pub struct Serializer<'w> {
     header: Header,
     output: String,
+    writer: Option<&'w mut dyn Write>,
     current_row: usize,
+    sparse: bool,
 }
 
-impl Serializer {
+impl<'w> Serializer<'w> {
     fn new() -> Self {
         Serializer {
             header: Header::new(),
             output: String::new(),
+            writer: None,
             current_row: 0,
+            sparse: false,
+        }
+    }
+
+    fn with_header(header: Header) -> Self {
+        Serializer {
+            header,
+            ..Serializer::new()
+        }
+    }
+
+    fn serialize_row<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        if self.sparse {
+            self.output += "{";
+        }
+        let (weight, n_columns) = {
+            let mut ser = RowSerializer::new(self);
+            value.serialize(&mut ser)?;
+            (ser.weight, ser.current_column)
+        };
+        if self.header.fixed && n_columns != self.header.attr_types.len() {
+            return Err(Error::WrongRowLength {
+                row: self.current_row,
+                expected: self.header.attr_types.len(),
+            });
+        }
+        if self.sparse {
+            self.output += "}";
+        }
+        match weight {
+            Some(w) if w != DEFAULT_WEIGHT => self.output += &format!(", {{{}}}", w),
+            _ => {}
+        }
+        self.output += "\n";
+        self.current_row += 1;
+
+        // when writing to a writer, only the current row is buffered
+        if let Some(ref mut writer) = self.writer {
+            writer.write_all(self.output.as_bytes())?;
+            self.output.clear();
+        }
+        Ok(())
+    }
+}
+
+/// Write ARFF data row by row, according to a header that is declared up front.
+///
+/// The header is written as soon as the writer is created. Each row is checked against the
+/// declared attributes before it is written, so a row that does not fit the header is rejected
+/// without leaving a partial row in the output.
+///
+/// Relational attributes are not supported; a header that declares one is rejected with
+/// `Error::RelationalAttribute`.
+pub struct ArffWriter<W: Write> {
+    writer: W,
+    serializer: Serializer<'static>,
+}
+
+impl<W: Write> ArffWriter<W> {
+    /// Create a writer and write the header to it.
+    pub fn new(mut writer: W, header: &parser::Header) -> Result<Self> {
+        let serializer = Serializer::with_header(Header::from_schema(header)?);
+        writer.write_all(serializer.header.to_string().as_bytes())?;
+        Ok(ArffWriter { writer, serializer })
+    }
+
+    /// Serialize a single data row.
+    pub fn write_row<T>(&mut self, row: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        let result = self.serializer.serialize_row(row);
+        if result.is_ok() {
+            self.writer.write_all(self.serializer.output.as_bytes())?;
+        }
+        self.serializer.output.clear();
+        result
+    }
+
+    /// Serialize all rows of a data set, including their instance weights and the comments
+    /// between them.
+    ///
+    /// The columns of the data set must match the attributes of the header. Either all rows are
+    /// written or, if one of them does not fit the header, none.
+    pub fn write_dataset(&mut self, dset: &DataSet) -> Result<()> {
+        let result = self.serialize_dataset(dset);
+        if result.is_ok() {
+            self.writer.write_all(self.serializer.output.as_bytes())?;
+        }
+        self.serializer.output.clear();
+        result
+    }
+
+    fn serialize_dataset(&mut self, dset: &DataSet) -> Result<()> {
+        let header = &self.serializer.header;
+        if dset.n_cols() != header.attr_names.len() {
+            return Err(Error::WrongRowLength {
+                row: self.serializer.current_row,
+                expected: header.attr_names.len(),
+            });
+        }
+        for (column, (name, expected)) in dset.col_names().zip(&header.attr_names).enumerate() {
+            if name != expected {
+                return Err(Error::WrongColumnName {
+                    column,
+                    expected: expected.to_string(),
+                    found: name.to_owned(),
+                });
+            }
+        }
+
+        let first_row = self.serializer.current_row;
+        let mut comments = dset.data_comments().iter().peekable();
+        for i in 0..dset.n_rows() {
+            write_comments(&mut self.serializer.output, &mut comments, i);
+            let row = Weighted::new(dset.row(i), dset.weight(i));
+            if let Err(e) = self.serializer.serialize_row(&row) {
+                self.serializer.current_row = first_row;
+                return Err(e);
+            }
+        }
+        write_comments(&mut self.serializer.output, &mut comments, usize::MAX);
+        Ok(())
+    }
+
+    /// Flush the underlying writer.
+    pub fn flush(&mut self) -> Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Get the underlying writer back.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: Read + Write + Seek> ArffWriter<W> {
+    /// Create a writer that appends rows to an existing ARFF file.
+    ///
+    /// Only the header of the file is parsed. New rows are checked against it, so values of
+    /// the wrong type or nominal values that are not declared in the file are rejected.
+    pub fn append(mut file: W) -> Result<Self> {
+        file.seek(SeekFrom::Start(0))?;
+        let header = {
+            let mut parser = Parser::from_reader(BufReader::new(&mut file));
+            let header = parser.parse_header();
+            parser.check_io(header)?
+        };
+        let serializer = Serializer::with_header(Header::from_schema(&header)?);
+
+        // make sure the first new row starts on a line of its own
+        let end = file.seek(SeekFrom::End(0))?;
+        if end > 0 {
+            let mut last = [0];
+            file.seek(SeekFrom::End(-1))?;
+            file.read_exact(&mut last)?;
+            if last[0] != b'\n' {
+                file.write_all(b"\n")?;
+            }
         }
+
+        Ok(ArffWriter {
+            writer: file,
+            serializer,
+        })
     }
 }
 
-impl<'a> ser::Serializer for &'a mut Serializer {
+impl<'a, 'w> ser::Serializer for &'a mut Serializer<'w> {
     type Ok = ();
     type Error = Error;
 
@@ -201,7 +551,7 @@ impl<'a> ser::Serializer for &'a mut Serializer {
     where
         T: ?Sized + Serialize,
     {
-        self.header.name = name;
+        self.header.name = name.into();
         value.serialize(self)
     }
 
@@ -231,7 +581,7 @@ impl<'a> ser::Serializer for &'a mut Serializer {
         name: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeTupleStruct> {
-        self.header.name = name;
+        self.header.name = name.into();
         Ok(self)
     }
 
@@ -264,7 +614,7 @@ impl<'a> ser::Serializer for &'a mut Serializer {
     }
 }
 
-impl<'a> ser::SerializeSeq for &'a mut Serializer {
+impl<'a, 'w> ser::SerializeSeq for &'a mut Serializer<'w> {
     type Ok = ();
     type Error = Error;
 
@@ -272,12 +622,7 @@ impl<'a> ser::SerializeSeq for &'a mut Serializer {
     where
         T: ?Sized + Serialize,
     {
-        {
-            let mut ser = RowSerializer::new(self);
-            value.serialize(&mut ser)?;
-        }
-        self.output += "\n";
-        Ok(())
+        self.serialize_row(value)
     }
 
     fn end(self) -> Result<()> {
@@ -285,7 +630,7 @@ impl<'a> ser::SerializeSeq for &'a mut Serializer {
     }
 }
 
-impl<'a> ser::SerializeTuple for &'a mut Serializer {
+impl<'a, 'w> ser::SerializeTuple for &'a mut Serializer<'w> {
     type Ok = ();
     type Error = Error;
 
@@ -293,13 +638,7 @@ impl<'a> ser::SerializeTuple for &'a mut Serializer {
     where
         T: ?Sized + Serialize,
     {
-        {
-            let mut ser = RowSerializer::new(self);
-            value.serialize(&mut ser)?;
-        }
-        self.output += "\n";
-        self.current_row += 1;
-        Ok(())
+        self.serialize_row(value)
     }
 
     fn end(self) -> Result<()> {
@@ -307,7 +646,7 @@ impl<'a> ser::SerializeTuple for &'a mut Serializer {
     }
 }
 
-impl<'a> ser::SerializeTupleStruct for &'a mut Serializer {
+impl<'a, 'w> ser::SerializeTupleStruct for &'a mut Serializer<'w> {
     type Ok = ();
     type Error = Error;
 
@@ -315,13 +654,7 @@ impl<'a> ser::SerializeTupleStruct for &'a mut Serializer {
     where
         T: ?Sized + Serialize,
     {
-        {
-            let mut ser = RowSerializer::new(self);
-            value.serialize(&mut ser)?;
-        }
-        self.output += "\n";
-        self.current_row += 1;
-        Ok(())
+        self.serialize_row(value)
     }
 
     fn end(self) -> Result<()> {
@@ -329,7 +662,7 @@ impl<'a> ser::SerializeTupleStruct for &'a mut Serializer {
     }
 }
 
-impl<'a> ser::SerializeTupleVariant for &'a mut Serializer {
+impl<'a, 'w> ser::SerializeTupleVariant for &'a mut Serializer<'w> {
     type Ok = ();
     type Error = Error;
 
@@ -345,7 +678,7 @@ impl<'a> ser::SerializeTupleVariant for &'a mut Serializer {
     }
 }
 
-impl<'a> ser::SerializeMap for &'a mut Serializer {
+impl<'a, 'w> ser::SerializeMap for &'a mut Serializer<'w> {
     type Ok = ();
     type Error = Error;
 
@@ -368,7 +701,7 @@ impl<'a> ser::SerializeMap for &'a mut Serializer {
     }
 }
 
-impl<'a> ser::SerializeStruct for &'a mut Serializer {
+impl<'a, 'w> ser::SerializeStruct for &'a mut Serializer<'w> {
     type Ok = ();
     type Error = Error;
 
@@ -384,7 +717,7 @@ impl<'a> ser::SerializeStruct for &'a mut Serializer {
     }
 }
 
-impl<'a> ser::SerializeStructVariant for &'a mut Serializer {
+impl<'a, 'w> ser::SerializeStructVariant for &'a mut Serializer<'w> {
     type Ok = ();
     type Error = Error;
 
@@ -407,6 +740,12 @@ pub struct RowSerializer<'a> {
     current_key: Option<&'static str>,
     row: usize,
     depth: u32,
+    in_date: bool,
+    sparse: bool,
+    n_values: usize,
+    weighted_fields: Option<usize>,
+    in_weight: bool,
+    weight: Option<f64>,
 }
 
 impl<'a> RowSerializer<'a> {
@@ -418,7 +757,82 @@ impl<'a> RowSerializer<'a> {
             current_key: None,
             row: ser.current_row,
             depth: 0,
+            in_date: false,
+            sparse: ser.sparse,
+            n_values: 0,
+            weighted_fields: None,
+            in_weight: false,
+            weight: None,
+        }
+    }
+
+    /// write the value of the current column
+    ///
+    /// In sparse mode, default values are left out and other values are prefixed with their
+    /// column index.
+    fn write_value(&mut self, value: &str, is_default: bool) {
+        if self.sparse {
+            if is_default {
+                return;
+            }
+            if self.n_values > 0 {
+                *self.output += ", ";
+            }
+            *self.output += &self.current_column.to_string();
+            *self.output += " ";
+        } else if self.current_column > 0 {
+            *self.output += ", ";
+        }
+        *self.output += value;
+        self.n_values += 1;
+    }
+
+    fn serialize_date(&mut self, millis: i64) -> Result<()> {
+        match self.get_current_dtype() {
+            None => self.set_current_dtype(DType::Date(DateFormat::default()))?,
+            Some(&DType::Date(_)) => {}
+            Some(_) => {
+                return Err(Error::InconsistentType {
+                    row: self.row,
+                    column: self.current_column,
+                })
+            }
         }
+        let value = match self.header.attr_types.get(self.current_column) {
+            Some(DType::Date(format)) => quote(&format.format(millis)),
+            _ => unreachable!(),
+        };
+        self.write_value(&value, millis == 0);
+        Ok(())
+    }
+
+    /// write a nominal value, adding it to the categories unless the header is fixed
+    ///
+    /// In sparse mode, only the first category can be left out as a default value.
+    fn serialize_nominal(&mut self, value: &str, is_default: bool) -> Result<()> {
+        let fixed = self.header.fixed;
+        let (row, column) = (self.row, self.current_column);
+
+        let is_first = match self.get_current_dtype_mut() {
+            Some(&mut DType::Nominal(ref mut categories)) => {
+                if fixed {
+                    if !categories.iter().any(|c| c == value) {
+                        return Err(Error::UndeclaredNominalValue {
+                            row,
+                            column,
+                            value: value.to_owned(),
+                        });
+                    }
+                } else if let Err(idx) = categories.binary_search_by(|c| (**c).cmp(value)) {
+                    categories.insert(idx, value.to_owned().into());
+                }
+                categories[0] == value
+            }
+            _ => return Err(Error::InconsistentType { row, column }),
+        };
+
+        self.write_value(&quote_if_needed(value), is_default && is_first);
+        Ok(())
     }
 
     fn get_current_dtype(&mut self) -> Option<&DType> {
@@ -429,7 +843,14 @@ impl<'a> RowSerializer<'a> {
         self.header.attr_types.get_mut(self.current_column)
     }
 
-    fn set_current_dtype(&mut self, dt: DType) {
+    fn set_current_dtype(&mut self, dt: DType) -> Result<()> {
+        if self.header.fixed {
+            return Err(Error::WrongRowLength {
+                row: self.row,
+                expected: self.header.attr_types.len(),
+            });
+        }
+
         if self.current_column > self.header.attr_types.len() {
             panic!("col_idx is too far ahead")
         }
@@ -439,6 +860,24 @@ impl<'a> RowSerializer<'a> {
         } else {
             self.header.attr_types[self.current_column] = dt;
         }
+        Ok(())
+    }
+
+    /// attach the name of the current column to an error about its value
+    fn attribute_error(&self, error: Error) -> Error {
+        match error {
+            Error::InconsistentType { .. } | Error::UndeclaredNominalValue { .. } => {
+                match self.get_current_name().or(self.current_key) {
+                    Some(name) => Error::Attribute {
+                        name: name.to_owned(),
+                        pos: None,
+                        error: Box::new(error),
+                    },
+                    None => error,
+                }
+            }
+            _ => error,
+        }
     }
 
     fn get_current_name(&self) -> Option<&str> {
@@ -449,6 +888,11 @@ impl<'a> RowSerializer<'a> {
     }
 
     fn set_current_name(&mut self, n: Cow<'static, str>) {
+        // too many columns for a fixed header are reported when setting the type
+        if self.header.fixed {
+            return;
+        }
+
         if self.current_column > self.header.attr_names.len() {
             panic!("col_idx is too far ahead")
         }
@@ -477,18 +921,10 @@ impl<'a, 'b> ser::Serializer for &'b mut RowSerializer<'a> {
         if self.depth == 0 {
             return Err(Error::UnexpectedType);
         }
-        match self.get_current_dtype() {
-            None => self.set_current_dtype(DType::Nominal(["f", "t"].iter().cloned().collect())),
-            Some(&DType::Nominal(_)) => {}
-            Some(_) => {
-                return Err(Error::InconsistentType {
-                    row: self.row,
-                    column: self.current_column,
-                })
-            }
+        if self.get_current_dtype().is_none() {
+            self.set_current_dtype(DType::Nominal(vec!["f".into(), "t".into()]))?;
         }
-        *self.output += if v { "t" } else { "f" };
-        Ok(())
+        self.serialize_nominal(if v { "t" } else { "f" }, !v)
     }
 
     fn serialize_i8(self, v: i8) -> Result<()> {
@@ -507,9 +943,12 @@ impl<'a, 'b> ser::Serializer for &'b mut RowSerializer<'a> {
         if self.depth == 0 {
             return Err(Error::UnexpectedType);
         }
+        if self.in_date {
+            return self.serialize_date(v);
+        }
         match self.get_current_dtype() {
-            None => self.set_current_dtype(DType::Numeric),
-            Some(&DType::Numeric) => {}
+            None => self.set_current_dtype(DType::Integer)?,
+            Some(&DType::Numeric) | Some(&DType::Integer) | Some(&DType::Real) => {}
             Some(_) => {
                 return Err(Error::InconsistentType {
                     row: self.row,
@@ -517,7 +956,7 @@ impl<'a, 'b> ser::Serializer for &'b mut RowSerializer<'a> {
                 })
             }
         }
-        *self.output += &v.to_string();
+        self.write_value(&v.to_string(), v == 0);
         Ok(())
     }
 
@@ -538,8 +977,8 @@ impl<'a, 'b> ser::Serializer for &'b mut RowSerializer<'a> {
             return Err(Error::UnexpectedType);
         }
         match self.get_current_dtype() {
-            None => self.set_current_dtype(DType::Numeric),
-            Some(&DType::Numeric) => {}
+            None => self.set_current_dtype(DType::Integer)?,
+            Some(&DType::Numeric) | Some(&DType::Integer) | Some(&DType::Real) => {}
             Some(_) => {
                 return Err(Error::InconsistentType {
                     row: self.row,
@@ -547,7 +986,7 @@ impl<'a, 'b> ser::Serializer for &'b mut RowSerializer<'a> {
                 })
             }
         }
-        *self.output += &v.to_string();
+        self.write_value(&v.to_string(), v == 0);
         Ok(())
     }
 
@@ -556,12 +995,20 @@ impl<'a, 'b> ser::Serializer for &'b mut RowSerializer<'a> {
     }
 
     fn serialize_f64(self, v: f64) -> Result<()> {
+        if self.in_weight {
+            self.weight = Some(v);
+            return Ok(());
+        }
         if self.depth == 0 {
             return Err(Error::UnexpectedType);
         }
+        let fixed = self.header.fixed;
         match self.get_current_dtype() {
-            None => self.set_current_dtype(DType::Numeric),
-            Some(&DType::Numeric) => {}
+            None => self.set_current_dtype(DType::Real)?,
+            Some(&DType::Numeric) | Some(&DType::Real) => {}
+            // whole numbers fit into a declared INTEGER attribute, others make it REAL
+            Some(&DType::Integer) if fixed && v.fract() == 0.0 => {}
+            Some(&DType::Integer) if !fixed => self.set_current_dtype(DType::Real)?,
             Some(_) => {
                 return Err(Error::InconsistentType {
                     row: self.row,
@@ -569,7 +1016,7 @@ impl<'a, 'b> ser::Serializer for &'b mut RowSerializer<'a> {
                 })
             }
         }
-        *self.output += &v.to_string();
+        self.write_value(&v.to_string(), v == 0.0);
         Ok(())
     }
 
@@ -581,9 +1028,12 @@ impl<'a, 'b> ser::Serializer for &'b mut RowSerializer<'a> {
         if self.depth == 0 {
             return Err(Error::UnexpectedType);
         }
+        // a fixed header may declare nominal columns that are filled from strings
+        let fixed = self.header.fixed;
         match self.get_current_dtype() {
-            None => self.set_current_dtype(DType::String),
+            None => self.set_current_dtype(DType::String)?,
             Some(&DType::String) => {}
+            Some(&DType::Nominal(_)) if fixed => return self.serialize_nominal(v, false),
             Some(_) => {
                 return Err(Error::InconsistentType {
                     row: self.row,
@@ -591,9 +1041,7 @@ impl<'a, 'b> ser::Serializer for &'b mut RowSerializer<'a> {
                 })
             }
         }
-        *self.output += "'";
-        *self.output += v;
-        *self.output += "'";
+        self.write_value(&quote(v), false);
         Ok(())
     }
 
@@ -605,7 +1053,7 @@ impl<'a, 'b> ser::Serializer for &'b mut RowSerializer<'a> {
         if self.depth == 0 {
             return Err(Error::UnexpectedType);
         }
-        *self.output += "?";
+        self.write_value("?", false);
         Ok(())
     }
 
@@ -638,32 +1086,21 @@ impl<'a, 'b> ser::Serializer for &'b mut RowSerializer<'a> {
         }
 
         if self.get_current_dtype().is_none() {
-            self.set_current_dtype(DType::Nominal(BTreeSet::new()));
-        }
-
-        let err;
-        if let Some(&mut DType::Nominal(ref mut variants)) = self.get_current_dtype_mut() {
-            variants.insert(variant);
-            err = false;
-        } else {
-            err = true;
-        }
-
-        if err {
-            Err(Error::InconsistentType {
-                row: self.row,
-                column: self.current_column,
-            })
-        } else {
-            *self.output += variant;
-            Ok(())
+            self.set_current_dtype(DType::Nominal(Vec::new()))?;
         }
+        self.serialize_nominal(variant, false)
     }
 
-    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<()>
+    fn serialize_newtype_struct<T>(self, name: &'static str, value: &T) -> Result<()>
     where
         T: ?Sized + Serialize,
     {
+        if name == DATE_TOKEN {
+            self.in_date = true;
+            let result = value.serialize(&mut *self);
+            self.in_date = false;
+            return result;
+        }
         value.serialize(self)
     }
 
@@ -681,7 +1118,8 @@ impl<'a, 'b> ser::Serializer for &'b mut RowSerializer<'a> {
     }
 
     fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
-        unimplemented!()
+        self.depth += 1;
+        Ok(self)
     }
 
     fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
@@ -691,9 +1129,13 @@ impl<'a, 'b> ser::Serializer for &'b mut RowSerializer<'a> {
 
     fn serialize_tuple_struct(
         self,
-        _name: &'static str,
+        name: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeTupleStruct> {
+        if name == WEIGHTED_TOKEN && self.depth == 0 {
+            self.weighted_fields = Some(0);
+            return Ok(self);
+        }
         unimplemented!()
     }
 
@@ -731,15 +1173,15 @@ impl<'a, 'b> ser::SerializeSeq for &'b mut RowSerializer<'a> {
     type Ok = ();
     type Error = Error;
 
-    fn serialize_element<T>(&mut self, _value: &T) -> Result<()>
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
     where
         T: ?Sized + Serialize,
     {
-        unimplemented!()
+        ser::SerializeTuple::serialize_element(self, value)
     }
 
     fn end(self) -> Result<()> {
-        unimplemented!()
+        ser::SerializeTuple::end(self)
     }
 }
 
@@ -759,12 +1201,10 @@ impl<'a, 'b> ser::SerializeTuple for &'b mut RowSerializer<'a> {
             self.set_current_name(name.into());
         }
 
-        if self.current_column > 0 && !self.output.ends_with(", ") {
-            *self.output += ", ";
-        }
-
         let last_idx = self.current_column;
-        value.serialize(&mut **self)?;
+        value
+            .serialize(&mut **self)
+            .map_err(|e| self.attribute_error(e))?;
         if last_idx == self.current_column {
             self.current_column += 1;
         }
@@ -781,15 +1221,31 @@ impl<'a, 'b> ser::SerializeTupleStruct for &'b mut RowSerializer<'a> {
     type Ok = ();
     type Error = Error;
 
-    fn serialize_field<T>(&mut self, _value: &T) -> Result<()>
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
     where
         T: ?Sized + Serialize,
     {
-        unimplemented!()
+        match self.weighted_fields {
+            // the data row
+            Some(0) => {
+                self.weighted_fields = Some(1);
+                value.serialize(&mut **self)
+            }
+            // the instance weight
+            Some(1) => {
+                self.weighted_fields = Some(2);
+                self.in_weight = true;
+                let result = value.serialize(&mut **self);
+                self.in_weight = false;
+                result
+            }
+            _ => unimplemented!(),
+        }
     }
 
     fn end(self) -> Result<()> {
-        unimplemented!()
+        self.weighted_fields = None;
+        Ok(())
     }
 }
 
@@ -840,14 +1296,23 @@ impl<'a, 'b> ser::SerializeStruct for &'b mut RowSerializer<'a> {
     where
         T: ?Sized + Serialize,
     {
-        if self.current_column > 0 {
-            *self.output += ", ";
-        }
         self.current_key = Some(key);
         let last_idx = self.current_column;
-        value.serialize(&mut **self)?;
+        value
+            .serialize(&mut **self)
+            .map_err(|e| self.attribute_error(e))?;
         if last_idx == self.current_column {
-            self.header.attr_names.push(key.into());
+            match self.get_current_name() {
+                None => self.set_current_name(key.into()),
+                Some(name) if name != key && self.header.fixed => {
+                    return Err(Error::WrongColumnName {
+                        column: last_idx,
+                        expected: name.to_owned(),
+                        found: key.to_owned(),
+                    })
+                }
+                Some(_) => {}
+            }
             self.current_column += 1;
         }
         Ok(())
@@ -938,16 +1403,16 @@ fn test_struct_data() {
 
     let expected = "@RELATION Data
 
-@ATTRIBUTE a NUMERIC
-@ATTRIBUTE b NUMERIC
-@ATTRIBUTE c NUMERIC
-@ATTRIBUTE d NUMERIC
-@ATTRIBUTE e NUMERIC
-@ATTRIBUTE f NUMERIC
-@ATTRIBUTE g NUMERIC
-@ATTRIBUTE h NUMERIC
-@ATTRIBUTE i NUMERIC
-@ATTRIBUTE j NUMERIC
+@ATTRIBUTE a INTEGER
+@ATTRIBUTE b INTEGER
+@ATTRIBUTE c INTEGER
+@ATTRIBUTE d INTEGER
+@ATTRIBUTE e INTEGER
+@ATTRIBUTE f INTEGER
+@ATTRIBUTE g INTEGER
+@ATTRIBUTE h INTEGER
+@ATTRIBUTE i REAL
+@ATTRIBUTE j REAL
 @ATTRIBUTE k STRING
 @ATTRIBUTE l {f, t}
 @ATTRIBUTE m {Blue, Red}
@@ -966,8 +1431,8 @@ fn test_struct_data() {
 fn test_primitive() {
     let expected = "@RELATION unnamed_data
 
-@ATTRIBUTE b NUMERIC
-@ATTRIBUTE a NUMERIC
+@ATTRIBUTE b INTEGER
+@ATTRIBUTE a INTEGER
 
 @DATA
 9, 42
@@ -999,11 +1464,11 @@ fn test_newtype_data() {
 
     let expected = "@RELATION Data
 
-@ATTRIBUTE col1 NUMERIC
-@ATTRIBUTE col2 NUMERIC
-@ATTRIBUTE col3 NUMERIC
-@ATTRIBUTE col4 NUMERIC
-@ATTRIBUTE col5 NUMERIC
+@ATTRIBUTE col1 INTEGER
+@ATTRIBUTE col2 INTEGER
+@ATTRIBUTE col3 INTEGER
+@ATTRIBUTE col4 INTEGER
+@ATTRIBUTE col5 INTEGER
 
 @DATA
 1, 2, 3, 4, 5
@@ -1043,9 +1508,9 @@ fn test_array_data() {
 
     let expected = "@RELATION Data
 
-@ATTRIBUTE rgb1 NUMERIC
-@ATTRIBUTE rgb2 NUMERIC
-@ATTRIBUTE rgb3 NUMERIC
+@ATTRIBUTE rgb1 INTEGER
+@ATTRIBUTE rgb2 INTEGER
+@ATTRIBUTE rgb3 INTEGER
 @ATTRIBUTE name STRING
 
 @DATA
@@ -1063,8 +1528,8 @@ fn test_array_data() {
 fn test_2darray() {
     let expected = "@RELATION unnamed_data
 
-@ATTRIBUTE col1 NUMERIC
-@ATTRIBUTE col2 NUMERIC
+@ATTRIBUTE col1 INTEGER
+@ATTRIBUTE col2 INTEGER
 
 @DATA
 42, 9
@@ -1079,10 +1544,10 @@ fn test_2darray() {
 fn test_mixed() {
     let expected = "@RELATION unnamed_data
 
-@ATTRIBUTE col1 NUMERIC
-@ATTRIBUTE col2 NUMERIC
-@ATTRIBUTE col3 NUMERIC
-@ATTRIBUTE col4 NUMERIC
+@ATTRIBUTE col1 INTEGER
+@ATTRIBUTE col2 INTEGER
+@ATTRIBUTE col3 INTEGER
+@ATTRIBUTE col4 INTEGER
 
 @DATA
 42, 9, 8, 7
@@ -1099,8 +1564,8 @@ fn test_mixed() {
 fn test_2dtuple() {
     let expected = "@RELATION unnamed_data
 
-@ATTRIBUTE col1 NUMERIC
-@ATTRIBUTE col2 NUMERIC
+@ATTRIBUTE col1 INTEGER
+@ATTRIBUTE col2 INTEGER
 
 @DATA
 1, 2
@@ -1117,6 +1582,461 @@ fn test_2dtuple() {
 fn test_missing() {
     assert_eq!(
         to_string(&[[Some(1)], [None], [Some(3)]]).unwrap(),
-        "@RELATION unnamed_data\n\n@ATTRIBUTE col1 NUMERIC\n\n@DATA\n1\n?\n3\n"
+        "@RELATION unnamed_data\n\n@ATTRIBUTE col1 INTEGER\n\n@DATA\n1\n?\n3\n"
+    );
+}
+
+#[test]
+fn test_date() {
+    use date::Date;
+
+    #[derive(Serialize)]
+    struct Row {
+        id: u8,
+        ts: Date,
+        opt: Option<Date>,
+    }
+
+    let data = vec![
+        Row {
+            id: 1,
+            ts: Date::from_millis(0),
+            opt: None,
+        },
+        Row {
+            id: 2,
+            ts: Date::from_ymd_hms(2001, 4, 3, 12, 12, 12).unwrap(),
+            opt: Some(Date::from_millis(-1000)),
+        },
+    ];
+
+    let expected = "@RELATION unnamed_data
+
+@ATTRIBUTE id INTEGER
+@ATTRIBUTE ts DATE \"yyyy-MM-dd'T'HH:mm:ss\"
+@ATTRIBUTE opt DATE \"yyyy-MM-dd'T'HH:mm:ss\"
+
+@DATA
+1, '1970-01-01T00:00:00', ?
+2, '2001-04-03T12:12:12', '1969-12-31T23:59:59'
+";
+
+    assert_eq!(to_string(&data).unwrap(), expected);
+}
+
+#[test]
+fn test_sparse() {
+    #[derive(Serialize)]
+    enum Color {
+        Red,
+        Green,
+    }
+
+    #[derive(Serialize)]
+    struct Row {
+        x: [f64; 3],
+        flag: bool,
+        name: &'static str,
+        color: Color,
+        opt: Option<i32>,
+    }
+
+    let data = vec![
+        Row {
+            x: [0.0, 1.5, 0.0],
+            flag: false,
+            name: "",
+            color: Color::Red,
+            opt: Some(0),
+        },
+        Row {
+            x: [0.0, 0.0, 0.0],
+            flag: true,
+            name: "abc",
+            color: Color::Green,
+            opt: None,
+        },
+    ];
+
+    let expected = "@RELATION unnamed_data
+
+@ATTRIBUTE x1 REAL
+@ATTRIBUTE x2 REAL
+@ATTRIBUTE x3 REAL
+@ATTRIBUTE flag {f, t}
+@ATTRIBUTE name STRING
+@ATTRIBUTE color {Green, Red}
+@ATTRIBUTE opt INTEGER
+
+@DATA
+{1 1.5, 4 '', 5 Red}
+{3 t, 4 'abc', 5 Green, 6 ?}
+";
+
+    assert_eq!(to_string_sparse(&data).unwrap(), expected);
+}
+
+#[test]
+fn test_weights() {
+    use weighted::Weighted;
+
+    let data = [Weighted::new((1, 2), 0.5), Weighted::new((3, 4), 1.0)];
+
+    assert_eq!(
+        to_string(&data).unwrap(),
+        "@RELATION unnamed_data\n\n@ATTRIBUTE col1 INTEGER\n@ATTRIBUTE col2 INTEGER\n\n@DATA\n1, 2, {0.5}\n3, 4\n"
+    );
+    assert_eq!(
+        to_string_sparse(&data).unwrap(),
+        "@RELATION unnamed_data\n\n@ATTRIBUTE col1 INTEGER\n@ATTRIBUTE col2 INTEGER\n\n@DATA\n{0 1, 1 2}, {0.5}\n{0 3, 1 4}\n"
+    );
+}
+
+#[test]
+fn test_escapes() {
+    #[derive(Serialize)]
+    enum Place {
+        #[serde(rename = "New York")]
+        NewYork,
+        Boston,
+    }
+
+    #[derive(Serialize)]
+    struct Row {
+        text: &'static str,
+        place: Place,
+    }
+
+    let data = [
+        Row {
+            text: "it's 100% \"fine\"\n\t\\",
+            place: Place::NewYork,
+        },
+        Row {
+            text: "",
+            place: Place::Boston,
+        },
+    ];
+
+    assert_eq!(
+        to_string(&data).unwrap(),
+        r#"@RELATION unnamed_data
+
+@ATTRIBUTE text STRING
+@ATTRIBUTE place {Boston, 'New York'}
+
+@DATA
+'it\'s 100\% \"fine\"\n\t\\', 'New York'
+'', Boston
+"#
+    );
+}
+
+#[test]
+fn test_quoted_names() {
+    #[derive(Serialize)]
+    enum Answer {
+        #[serde(rename = "yes, please")]
+        Yes,
+        No,
+    }
+
+    #[derive(Serialize)]
+    struct Row {
+        #[serde(rename = "first name")]
+        name: &'static str,
+        #[serde(rename = "100%")]
+        full: u8,
+        #[serde(rename = "{x}")]
+        x: Answer,
+    }
+
+    #[derive(Serialize)]
+    #[serde(rename = "my data, v2")]
+    struct Data(Vec<Row>);
+
+    let data = Data(vec![
+        Row {
+            name: "a",
+            full: 1,
+            x: Answer::Yes,
+        },
+        Row {
+            name: "b",
+            full: 0,
+            x: Answer::No,
+        },
+    ]);
+
+    assert_eq!(
+        to_string(&data).unwrap(),
+        r#"@RELATION 'my data, v2'
+
+@ATTRIBUTE 'first name' STRING
+@ATTRIBUTE '100\%' INTEGER
+@ATTRIBUTE '{x}' {No, 'yes, please'}
+
+@DATA
+'a', 1, 'yes, please'
+'b', 0, No
+"#
     );
 }
+
+#[test]
+fn test_to_writer() {
+    #[derive(Serialize)]
+    enum Color {
+        Red,
+        Green,
+    }
+
+    #[derive(Serialize)]
+    struct Row {
+        x: f64,
+        color: Color,
+    }
+
+    #[derive(Serialize)]
+    struct Colors(Vec<Row>);
+
+    // the second category only appears in the last row, but must be declared in the header
+    let data = Colors(vec![
+        Row {
+            x: 0.0,
+            color: Color::Red,
+        },
+        Row {
+            x: 2.5,
+            color: Color::Green,
+        },
+    ]);
+
+    let mut output = Vec::new();
+    to_writer(&mut output, &data).unwrap();
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        to_string(&data).unwrap()
+    );
+
+    let mut output = Vec::new();
+    to_writer_sparse(&mut output, &data).unwrap();
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        to_string_sparse(&data).unwrap()
+    );
+}
+
+#[test]
+fn test_arff_writer() {
+    use parser::{Attribute, DType as AttrType, Header as Schema};
+
+    #[derive(Serialize)]
+    enum Color {
+        Red,
+        Blue,
+    }
+
+    #[derive(Serialize)]
+    struct Row {
+        x: f64,
+        color: Color,
+    }
+
+    #[derive(Serialize)]
+    struct Swapped {
+        color: Color,
+        x: f64,
+    }
+
+    let schema = Schema {
+        name: "simulation".to_owned(),
+        attrs: vec![
+            Attribute {
+                name: "x".to_owned(),
+                dtype: AttrType::Numeric,
+            },
+            Attribute {
+                name: "color".to_owned(),
+                dtype: AttrType::Nominal(vec!["Red".to_owned(), "Green".to_owned()]),
+            },
+        ],
+        relation_comments: vec![],
+        comments: vec![],
+    };
+
+    let mut writer = ArffWriter::new(Vec::new(), &schema).unwrap();
+    writer
+        .write_row(&Row {
+            x: 1.5,
+            color: Color::Red,
+        })
+        .unwrap();
+    writer.write_row(&(2, "Green")).unwrap();
+
+    assert_eq!(
+        writer.write_row(&Row {
+            x: 0.0,
+            color: Color::Blue,
+        }),
+        Err(Error::Attribute {
+            name: "color".to_owned(),
+            pos: None,
+            error: Box::new(Error::UndeclaredNominalValue {
+                row: 2,
+                column: 1,
+                value: "Blue".to_owned(),
+            }),
+        })
+    );
+    assert_eq!(
+        writer.write_row(&Swapped {
+            color: Color::Red,
+            x: 0.0,
+        }),
+        Err(Error::Attribute {
+            name: "x".to_owned(),
+            pos: None,
+            error: Box::new(Error::InconsistentType { row: 2, column: 0 }),
+        })
+    );
+    assert_eq!(
+        writer.write_row(&("Red", 1)),
+        Err(Error::Attribute {
+            name: "x".to_owned(),
+            pos: None,
+            error: Box::new(Error::InconsistentType { row: 2, column: 0 }),
+        })
+    );
+    assert_eq!(
+        writer.write_row(&[1]),
+        Err(Error::WrongRowLength {
+            row: 2,
+            expected: 2,
+        })
+    );
+    assert_eq!(
+        writer.write_row(&(1, "Red", 2)),
+        Err(Error::WrongRowLength {
+            row: 2,
+            expected: 2,
+        })
+    );
+
+    writer.write_row(&(3, "Red")).unwrap();
+
+    let output = String::from_utf8(writer.into_inner()).unwrap();
+    assert_eq!(
+        output,
+        "@RELATION simulation
+
+@ATTRIBUTE x NUMERIC
+@ATTRIBUTE color {Red, Green}
+
+@DATA
+1.5, Red
+2, Green
+3, Red
+"
+    );
+}
+
+#[test]
+fn test_numeric_types() {
+    use parser::{Attribute, DType as AttrType, Header as Schema};
+
+    let data = [(1u8, -2i64, 0.5f32, 1.5f64), (2, 3, 1.0, 2.0)];
+
+    assert_eq!(
+        to_string(&data).unwrap(),
+        "@RELATION unnamed_data
+
+@ATTRIBUTE col1 INTEGER
+@ATTRIBUTE col2 INTEGER
+@ATTRIBUTE col3 REAL
+@ATTRIBUTE col4 REAL
+
+@DATA
+1, -2, 0.5, 1.5
+2, 3, 1, 2
+"
+    );
+
+    let schema = Schema {
+        name: "counts".to_owned(),
+        attrs: vec![Attribute {
+            name: "n".to_owned(),
+            dtype: AttrType::Integer,
+        }],
+        relation_comments: vec![],
+        comments: vec![],
+    };
+
+    let mut writer = ArffWriter::new(Vec::new(), &schema).unwrap();
+    writer.write_row(&[1]).unwrap();
+    writer.write_row(&[2.0]).unwrap();
+    assert_eq!(
+        writer.write_row(&[1.5]),
+        Err(Error::Attribute {
+            name: "n".to_owned(),
+            pos: None,
+            error: Box::new(Error::InconsistentType { row: 2, column: 0 }),
+        })
+    );
+
+    let output = String::from_utf8(writer.into_inner()).unwrap();
+    assert_eq!(
+        output,
+        "@RELATION counts\n\n@ATTRIBUTE n INTEGER\n\n@DATA\n1\n2\n"
+    );
+}
+
+#[test]
+fn test_arff_writer_names() {
+    use parser::{Attribute, DType as AttrType, Header as Schema};
+
+    #[derive(Serialize)]
+    struct Row {
+        a: f64,
+        c: f64,
+    }
+
+    let schema = Schema {
+        name: "data".to_owned(),
+        attrs: vec![
+            Attribute {
+                name: "a".to_owned(),
+                dtype: AttrType::Numeric,
+            },
+            Attribute {
+                name: "b".to_owned(),
+                dtype: AttrType::Numeric,
+            },
+        ],
+        relation_comments: vec![],
+        comments: vec![],
+    };
+
+    let mut writer = ArffWriter::new(Vec::new(), &schema).unwrap();
+    assert_eq!(
+        writer.write_row(&Row { a: 1.0, c: 2.0 }),
+        Err(Error::WrongColumnName {
+            column: 1,
+            expected: "b".to_owned(),
+            found: "c".to_owned(),
+        })
+    );
+}
+
+#[test]
+fn test_arff_writer_relational() {
+    let schema = ::de::read_header(
+        "@RELATION x\n@ATTRIBUTE bag RELATIONAL\n@ATTRIBUTE a NUMERIC\n@END bag\n@DATA\n",
+    )
+    .unwrap();
+
+    match ArffWriter::new(Vec::new(), &schema) {
+        Err(e) => assert_eq!(e, Error::RelationalAttribute("bag".to_owned())),
+        Ok(_) => panic!("relational attribute should be rejected"),
+    }
+}
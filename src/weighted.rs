@@ -0,0 +1,88 @@
+// Copyright 2018 Martin Billinger
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Support for instance weights.
+//!
+//! Weka allows each data row to end with a weight in curly braces, e.g. `1, 2, {0.75}`. Rows
+//! without such a weight have a weight of 1.
+
+use std::fmt;
+use std::marker::PhantomData;
+
+use serde::de::{self, Deserialize, Deserializer, SeqAccess, Visitor};
+use serde::ser::{Serialize, SerializeTupleStruct, Serializer};
+
+/// Name of the tuple struct that identifies a `Weighted` row to the ARFF (de)serializers.
+pub(crate) const WEIGHTED_TOKEN: &str = "$arff::private::Weighted";
+
+/// The weight of rows that do not specify one.
+pub const DEFAULT_WEIGHT: f64 = 1.0;
+
+/// A data row together with its instance weight.
+///
+/// Deserialize rows into this type to read the instance weights of a data set; plain row types
+/// simply ignore them. When serializing, weights other than 1 are appended to the row.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Weighted<T> {
+    pub row: T,
+    pub weight: f64,
+}
+
+impl<T> Weighted<T> {
+    pub fn new(row: T, weight: f64) -> Self {
+        Weighted { row, weight }
+    }
+}
+
+impl<T> From<T> for Weighted<T> {
+    fn from(row: T) -> Self {
+        Weighted::new(row, DEFAULT_WEIGHT)
+    }
+}
+
+impl<T: Serialize> Serialize for Weighted<T> {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut s = serializer.serialize_tuple_struct(WEIGHTED_TOKEN, 2)?;
+        s.serialize_field(&self.row)?;
+        s.serialize_field(&self.weight)?;
+        s.end()
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Weighted<T> {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct WeightedVisitor<T>(PhantomData<T>);
+
+        impl<'de, T: Deserialize<'de>> Visitor<'de> for WeightedVisitor<T> {
+            type Value = Weighted<T>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a weighted data row")
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> std::result::Result<Weighted<T>, A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                let row = seq
+                    .next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+                let weight = seq.next_element()?.unwrap_or(DEFAULT_WEIGHT);
+                Ok(Weighted { row, weight })
+            }
+        }
+
+        deserializer.deserialize_tuple_struct(WEIGHTED_TOKEN, 2, WeightedVisitor(PhantomData))
+    }
+}
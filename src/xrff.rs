@@ -0,0 +1,649 @@
+// Copyright 2018 Martin Billinger
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Import and export of XRFF, Weka's XML variant of ARFF.
+//!
+//! An XRFF file describes the same attributes and instances as an ARFF file. In addition, it
+//! can mark one attribute as the class attribute and attach metadata properties to each
+//! attribute. Requires the `xrff` feature.
+
+use std::io::{BufRead, Read, Write};
+use std::path::Path;
+
+use xml::attribute::OwnedAttribute;
+use xml::common::Position;
+use xml::name::OwnedName;
+use xml::reader::{EventReader, ParserConfig, XmlEvent};
+
+use super::date::DateFormat;
+use super::dynamic::{DataSet, Value};
+use super::error::{Error, Result};
+use super::file::open_file;
+use super::parser::{Attribute, DType, Parser, TextPos};
+use super::ser::quote;
+
+/// The metadata properties of an attribute as `(name, value)` pairs, in file order
+pub type Metadata = Vec<(String, String)>;
+
+/// A data set together with the additional information stored in XRFF files
+#[derive(Debug, Clone, PartialEq)]
+pub struct Xrff {
+    /// The data, including instance weights
+    pub data: DataSet,
+    /// Index of the attribute marked as class attribute
+    pub class_index: Option<usize>,
+    /// Metadata of each attribute; attributes without metadata may be left out at the end
+    pub metadata: Vec<Metadata>,
+}
+
+impl Xrff {
+    /// Wrap a data set without class attribute or metadata.
+    pub fn new(data: DataSet) -> Self {
+        Xrff {
+            data,
+            class_index: None,
+            metadata: Vec::new(),
+        }
+    }
+}
+
+/// Read an XRFF data set from a reader.
+pub fn from_reader<R: BufRead>(reader: R) -> Result<Xrff> {
+    let mut reader = XmlReader::new(reader);
+
+    let dataset = reader.expect_start("dataset")?;
+    let relation = dataset.require_attr("name")?.to_owned();
+
+    let header = reader.expect_start("header")?;
+    let header = reader.read_element(header)?;
+    let declarations = header
+        .child("attributes")
+        .ok_or_else(|| header.error("expected <attributes>"))?;
+    let (attrs, class_index, metadata) = parse_attributes(declarations)?;
+
+    let mut data = DataSet::from_attributes(relation, attrs.clone())?;
+
+    reader.expect_start("body")?;
+    reader.expect_start("instances")?;
+    while let Some(start) = reader.next_start()? {
+        if start.name != "instance" {
+            return Err(start.error("expected <instance>"));
+        }
+        let instance = reader.read_element(start)?;
+        let row = instance_row(&instance, &attrs)?;
+        data.parse_rows(&mut Parser::from_string(row))
+            .map_err(|e| instance.error(&format!("invalid instance: {}", e)))?;
+    }
+
+    Ok(Xrff {
+        data,
+        class_index,
+        metadata,
+    })
+}
+
+/// Read an XRFF data set from a string.
+pub fn from_str(input: &str) -> Result<Xrff> {
+    from_reader(input.as_bytes())
+}
+
+/// Read an XRFF data set from a file, which may be gzip compressed.
+pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Xrff> {
+    from_reader(open_file(path)?)
+}
+
+/// Write a data set in XRFF format.
+pub fn to_writer<W: Write>(mut writer: W, xrff: &Xrff) -> Result<()> {
+    let attrs = xrff.data.schema().attrs;
+
+    let mut out = String::new();
+    out += "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n";
+    out += &format!("<dataset name=\"{}\">\n", escape(xrff.data.name()));
+    out += "  <header>\n";
+    write_attributes(&mut out, &attrs, xrff.class_index, &xrff.metadata, 2);
+    out += "  </header>\n";
+    out += "  <body>\n";
+    out += "    <instances>\n";
+    writer.write_all(out.as_bytes())?;
+
+    for i in 0..xrff.data.n_rows() {
+        out.clear();
+        write_instance(&mut out, &xrff.data, i, &attrs, 3);
+        writer.write_all(out.as_bytes())?;
+    }
+
+    writer.write_all(b"    </instances>\n  </body>\n</dataset>\n")?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Write a data set in XRFF format into a string.
+pub fn to_string(xrff: &Xrff) -> Result<String> {
+    let mut output = Vec::new();
+    to_writer(&mut output, xrff)?;
+    Ok(String::from_utf8(output)?)
+}
+
+/// parse the attribute declarations, the class attribute marker and the metadata
+fn parse_attributes(
+    declarations: &Element,
+) -> Result<(Vec<Attribute>, Option<usize>, Vec<Metadata>)> {
+    let mut attrs = Vec::new();
+    let mut class_index = None;
+    let mut metadata = Vec::new();
+
+    for (i, decl) in declarations.children("attribute").enumerate() {
+        let name = decl.require_attr("name")?.to_owned();
+
+        let dtype = match decl.require_attr("type")? {
+            "numeric" => DType::Numeric,
+            "string" => DType::String,
+            "date" => match decl.attr("format") {
+                Some(pattern) => DType::Date(DateFormat::new(pattern)?),
+                None => DType::Date(DateFormat::default()),
+            },
+            "nominal" => DType::Nominal(match decl.child("labels") {
+                Some(labels) => labels
+                    .children("label")
+                    .map(|label| label.text.clone())
+                    .collect(),
+                None => Vec::new(),
+            }),
+            "relational" => {
+                let nested = decl
+                    .child("attributes")
+                    .ok_or_else(|| decl.error("expected <attributes>"))?;
+                DType::Relational(parse_attributes(nested)?.0)
+            }
+            other => return Err(Error::InvalidColumnType(decl.pos, other.to_owned())),
+        };
+
+        if decl.attr("class") == Some("yes") {
+            class_index = Some(i);
+        }
+
+        metadata.push(match decl.child("metadata") {
+            Some(properties) => properties
+                .children("property")
+                .map(|p| Ok((p.require_attr("name")?.to_owned(), p.text.clone())))
+                .collect::<Result<_>>()?,
+            None => Vec::new(),
+        });
+
+        attrs.push(Attribute { name, dtype });
+    }
+
+    Ok((attrs, class_index, metadata))
+}
+
+/// translate an `<instance>` element into an ARFF data row
+fn instance_row(instance: &Element, attrs: &[Attribute]) -> Result<String> {
+    let sparse = instance.attr("type") == Some("sparse");
+
+    let mut row = String::new();
+    if sparse {
+        row += "{";
+    }
+
+    for (i, value) in instance.children("value").enumerate() {
+        let column = if sparse {
+            // sparse indices start at 1 in XRFF
+            match value.require_attr("index")?.trim().parse::<usize>() {
+                Ok(idx) if idx > 0 => idx - 1,
+                _ => return Err(value.error("expected attribute index")),
+            }
+        } else {
+            i
+        };
+        let attr = attrs
+            .get(column)
+            .ok_or_else(|| value.error("more values than attributes"))?;
+
+        if i > 0 {
+            row += ", ";
+        }
+        if sparse {
+            row += &format!("{} ", column);
+        }
+        row += &value_text(value, attr)?;
+    }
+
+    if sparse {
+        row += "}";
+    }
+
+    if let Some(weight) = instance.attr("weight") {
+        let weight: f64 = weight
+            .trim()
+            .parse()
+            .map_err(|_| instance.error("expected instance weight"))?;
+        row += &format!(", {{{}}}", weight);
+    }
+
+    Ok(row)
+}
+
+/// translate a `<value>` element into an ARFF value
+fn value_text(value: &Element, attr: &Attribute) -> Result<String> {
+    if let DType::Relational(ref attrs) = attr.dtype {
+        if let Some(instances) = value.child("instances") {
+            let rows = instances
+                .children("instance")
+                .map(|instance| instance_row(instance, attrs))
+                .collect::<Result<Vec<_>>>()?;
+            return Ok(quote(&rows.join("\n")));
+        }
+    }
+
+    let text = value.text.trim();
+    if text == "?" {
+        return Ok("?".to_owned());
+    }
+
+    match attr.dtype {
+        DType::Numeric | DType::Integer | DType::Real => match text.parse::<f64>() {
+            Ok(_) => Ok(text.to_owned()),
+            Err(_) => Err(value.error("expected numeric value")),
+        },
+        _ => Ok(quote(&value.text)),
+    }
+}
+
+fn write_attributes(
+    out: &mut String,
+    attrs: &[Attribute],
+    class_index: Option<usize>,
+    metadata: &[Metadata],
+    depth: usize,
+) {
+    let indent = "  ".repeat(depth);
+    *out += &format!("{}<attributes>\n", indent);
+
+    for (i, attr) in attrs.iter().enumerate() {
+        let (typename, format) = match attr.dtype {
+            // XRFF does not distinguish integer and real attributes
+            DType::Numeric | DType::Integer | DType::Real => ("numeric", None),
+            DType::String => ("string", None),
+            DType::Date(ref format) => ("date", Some(format.pattern())),
+            DType::Nominal(_) => ("nominal", None),
+            DType::Relational(_) => ("relational", None),
+        };
+
+        *out += &format!("{}  <attribute", indent);
+        if class_index == Some(i) {
+            *out += " class=\"yes\"";
+        }
+        *out += &format!(" name=\"{}\" type=\"{}\"", escape(&attr.name), typename);
+        if let Some(pattern) = format {
+            *out += &format!(" format=\"{}\"", escape(pattern));
+        }
+
+        let properties = metadata.get(i).map_or(&[][..], |m| &m[..]);
+        let has_content = match attr.dtype {
+            DType::Nominal(_) | DType::Relational(_) => true,
+            _ => !properties.is_empty(),
+        };
+        if !has_content {
+            *out += "/>\n";
+            continue;
+        }
+        *out += ">\n";
+
+        match attr.dtype {
+            DType::Nominal(ref labels) => {
+                *out += &format!("{}    <labels>\n", indent);
+                for label in labels {
+                    *out += &format!("{}      <label>{}</label>\n", indent, escape(label));
+                }
+                *out += &format!("{}    </labels>\n", indent);
+            }
+            DType::Relational(ref nested) => {
+                write_attributes(out, nested, None, &[], depth + 2);
+            }
+            _ => {}
+        }
+
+        if !properties.is_empty() {
+            *out += &format!("{}    <metadata>\n", indent);
+            for (name, value) in properties {
+                *out += &format!(
+                    "{}      <property name=\"{}\">{}</property>\n",
+                    indent,
+                    escape(name),
+                    escape(value)
+                );
+            }
+            *out += &format!("{}    </metadata>\n", indent);
+        }
+
+        *out += &format!("{}  </attribute>\n", indent);
+    }
+
+    *out += &format!("{}</attributes>\n", indent);
+}
+
+fn write_instance(out: &mut String, data: &DataSet, row: usize, attrs: &[Attribute], depth: usize) {
+    let indent = "  ".repeat(depth);
+
+    let weight = data.weight(row);
+    if weight == 1.0 {
+        *out += &format!("{}<instance>\n", indent);
+    } else {
+        *out += &format!("{}<instance weight=\"{}\">\n", indent, weight);
+    }
+
+    for (col, attr) in attrs.iter().enumerate() {
+        let text = match data.item(row, col) {
+            Value::Missing => "?".to_owned(),
+            Value::U8(v) => v.to_string(),
+            Value::U16(v) => v.to_string(),
+            Value::U32(v) => v.to_string(),
+            Value::U64(v) => v.to_string(),
+            Value::I8(v) => v.to_string(),
+            Value::I16(v) => v.to_string(),
+            Value::I32(v) => v.to_string(),
+            Value::I64(v) => v.to_string(),
+            Value::F64(v) => v.to_string(),
+            Value::String(s) => escape(s),
+            Value::Date(millis) => match attr.dtype {
+                DType::Date(ref format) => escape(&format.format(millis)),
+                _ => unreachable!(),
+            },
+            Value::Nominal(idx, categories) => escape(&categories[idx]),
+            Value::Relational(bag) => {
+                let nested = bag.schema().attrs;
+                *out += &format!("{}  <value>\n", indent);
+                *out += &format!("{}    <instances>\n", indent);
+                for i in 0..bag.n_rows() {
+                    write_instance(out, bag, i, &nested, depth + 3);
+                }
+                *out += &format!("{}    </instances>\n", indent);
+                *out += &format!("{}  </value>\n", indent);
+                continue;
+            }
+        };
+        *out += &format!("{}  <value>{}</value>\n", indent, text);
+    }
+
+    *out += &format!("{}</instance>\n", indent);
+}
+
+/// Replace characters that have a special meaning in XML with entities.
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for ch in s.chars() {
+        match ch {
+            '&' => escaped += "&amp;",
+            '<' => escaped += "&lt;",
+            '>' => escaped += "&gt;",
+            '"' => escaped += "&quot;",
+            '\'' => escaped += "&apos;",
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+/// An XML element with its attributes, child elements and text content
+struct Element {
+    name: String,
+    attrs: Vec<(String, String)>,
+    children: Vec<Element>,
+    text: String,
+    pos: TextPos,
+}
+
+impl Element {
+    fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs
+            .iter()
+            .find(|attr| attr.0 == name)
+            .map(|attr| &attr.1[..])
+    }
+
+    fn require_attr(&self, name: &str) -> Result<&str> {
+        self.attr(name)
+            .ok_or_else(|| self.error(&format!("expected XML attribute `{}`", name)))
+    }
+
+    fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    fn children<'a>(&'a self, name: &'a str) -> Box<dyn Iterator<Item = &'a Element> + 'a> {
+        Box::new(self.children.iter().filter(move |child| child.name == name))
+    }
+
+    fn error(&self, msg: &str) -> Error {
+        Error::Xrff(self.pos, msg.to_owned())
+    }
+}
+
+/// Pull XML elements from a reader
+struct XmlReader<R: Read> {
+    events: EventReader<R>,
+}
+
+impl<R: Read> XmlReader<R> {
+    fn new(reader: R) -> Self {
+        let config = ParserConfig::new()
+            .whitespace_to_characters(true)
+            .cdata_to_characters(true);
+        XmlReader {
+            events: config.create_reader(reader),
+        }
+    }
+
+    fn pos(&self) -> TextPos {
+        let pos = self.events.position();
+        TextPos::new(pos.row as usize + 1, pos.column as usize + 1)
+    }
+
+    fn next(&mut self) -> Result<XmlEvent> {
+        self.events
+            .next()
+            .map_err(|e| Error::Xrff(self.pos(), e.msg().to_owned()))
+    }
+
+    /// read up to the start of the next child element, or `None` at the end of the parent
+    fn next_start(&mut self) -> Result<Option<Element>> {
+        loop {
+            match self.next()? {
+                XmlEvent::StartElement {
+                    name, attributes, ..
+                } => return Ok(Some(self.start_element(name, attributes))),
+                XmlEvent::EndElement { .. } => return Ok(None),
+                XmlEvent::EndDocument => return Err(Error::Eof),
+                _ => {}
+            }
+        }
+    }
+
+    /// an element without content yet
+    fn start_element(&self, name: OwnedName, attributes: Vec<OwnedAttribute>) -> Element {
+        Element {
+            name: name.local_name,
+            attrs: attributes
+                .into_iter()
+                .map(|a| (a.name.local_name, a.value))
+                .collect(),
+            children: Vec::new(),
+            text: String::new(),
+            pos: self.pos(),
+        }
+    }
+
+    fn expect_start(&mut self, name: &str) -> Result<Element> {
+        match self.next_start()? {
+            Some(ref element) if element.name != name => {
+                Err(element.error(&format!("expected <{}>", name)))
+            }
+            Some(element) => Ok(element),
+            None => Err(Error::Xrff(self.pos(), format!("expected <{}>", name))),
+        }
+    }
+
+    /// read the content of an element whose start has just been read
+    fn read_element(&mut self, mut element: Element) -> Result<Element> {
+        loop {
+            match self.next()? {
+                XmlEvent::StartElement {
+                    name, attributes, ..
+                } => {
+                    let child = self.start_element(name, attributes);
+                    element.children.push(self.read_element(child)?);
+                }
+                XmlEvent::EndElement { .. } => return Ok(element),
+                XmlEvent::Characters(text) => element.text += &text,
+                XmlEvent::EndDocument => return Err(Error::Eof),
+                _ => {}
+            }
+        }
+    }
+}
+
+#[test]
+fn test_read_xrff() {
+    let input = r#"<?xml version="1.0" encoding="utf-8"?>
+<!DOCTYPE dataset
+[
+   <!ELEMENT dataset (header,body)>
+   <!ATTLIST dataset name CDATA #REQUIRED>
+   <!ATTLIST dataset version CDATA "3.5.4">
+]
+>
+<dataset name="iris &amp; more" version="3.5.4">
+  <header>
+    <attributes>
+      <attribute name="sepallength" type="numeric">
+        <metadata>
+          <property name="unit">cm</property>
+        </metadata>
+      </attribute>
+      <attribute name="note" type="string"/>
+      <attribute name="seen" type="date" format="yyyy-MM-dd"/>
+      <attribute class="yes" name="class" type="nominal">
+        <labels>
+          <label>Iris-setosa</label>
+          <label>Iris &lt;versicolor&gt;</label>
+        </labels>
+      </attribute>
+    </attributes>
+  </header>
+  <body>
+    <instances>
+      <!-- a dense instance -->
+      <instance>
+        <value>5.1</value>
+        <value>it's, fine</value>
+        <value>2018-03-01</value>
+        <value>Iris-setosa</value>
+      </instance>
+      <instance type="sparse" weight="0.5">
+        <value index="2"><![CDATA[a <b>]]></value>
+        <value index="4">Iris &lt;versicolor&gt;</value>
+      </instance>
+      <instance>
+        <value>?</value>
+        <value>?</value>
+        <value>?</value>
+        <value>?</value>
+      </instance>
+    </instances>
+  </body>
+</dataset>
+"#;
+
+    let xrff = from_str(input).unwrap();
+    let data = &xrff.data;
+
+    assert_eq!(data.name(), "iris & more");
+    assert_eq!(xrff.class_index, Some(3));
+    assert_eq!(
+        xrff.metadata,
+        vec![
+            vec![("unit".to_owned(), "cm".to_owned())],
+            vec![],
+            vec![],
+            vec![],
+        ]
+    );
+
+    assert_eq!(data.n_rows(), 3);
+    assert_eq!(data.item(0, 0), Value::F64(5.1));
+    assert_eq!(data.item(0, 1), Value::String("it's, fine"));
+    assert_eq!(data.item(0, 2), Value::Date(1519862400000));
+    assert_eq!(data.item(0, 3).as_str(), Ok("Iris-setosa"));
+    assert_eq!(data.item(1, 0), Value::F64(0.0));
+    assert_eq!(data.item(1, 1), Value::String("a <b>"));
+    assert_eq!(data.item(1, 3).as_str(), Ok("Iris <versicolor>"));
+    assert_eq!(data.item(2, 0), Value::Missing);
+    assert_eq!(data.weights(), &[1.0, 0.5, 1.0]);
+
+    assert_eq!(from_str(&to_string(&xrff).unwrap()).unwrap(), xrff);
+}
+
+#[test]
+fn test_xrff_relational() {
+    let data = DataSet::from_str(
+        "@RELATION musk
+@ATTRIBUTE id {a, b}
+@ATTRIBUTE bag RELATIONAL
+  @ATTRIBUTE f NUMERIC
+@END bag
+@DATA
+a, '1\\n2.5'
+b, ''
+",
+    )
+    .unwrap();
+
+    let xrff = Xrff::new(data);
+    let output = to_string(&xrff).unwrap();
+    assert!(output.contains("<attribute name=\"bag\" type=\"relational\">"));
+
+    let read = from_str(&output).unwrap();
+    assert_eq!(read.data, xrff.data);
+    assert_eq!(read.class_index, None);
+}
+
+#[test]
+fn test_xrff_errors() {
+    let input = r#"<dataset name="x">
+  <header>
+    <attributes>
+      <attribute name="a" type="numeric"/>
+    </attributes>
+  </header>
+  <body>
+    <instances>
+      <instance><value>abc</value></instance>
+    </instances>
+  </body>
+</dataset>"#;
+    assert_eq!(
+        from_str(input),
+        Err(Error::Xrff(
+            TextPos::new(9, 17),
+            "expected numeric value".to_owned()
+        ))
+    );
+
+    let input = r#"<dataset name="x"><header><attributes>
+        <attribute name="a" type="integer"/>
+    </attributes></header></dataset>"#;
+    assert_eq!(
+        from_str(input),
+        Err(Error::InvalidColumnType(
+            TextPos::new(2, 9),
+            "integer".to_owned()
+        ))
+    );
+
+    assert!(from_str("<dataset name='x'><header>").is_err());
+}
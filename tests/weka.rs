@@ -0,0 +1,223 @@
+// Copyright 2018 Martin Billinger
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Conformance tests with ARFF files as Weka reads and writes them.
+//!
+//! The files in `tests/weka` are data sets that ship with Weka and examples from the ARFF
+//! documentation. `airline.arff` and `labor.arff` are shortened to their first rows.
+
+extern crate arff;
+
+use arff::dynamic::{DataSet, Value};
+use arff::{ArffWriter, DType, Header};
+
+/// parse a file of the corpus and check that the data set agrees with the header
+fn parse(input: &str) -> (Header, DataSet) {
+    let header = arff::read_header(input).unwrap();
+    let dset = DataSet::from_str(input).unwrap();
+    assert_eq!(dset.schema(), header);
+    (header, dset)
+}
+
+/// like `parse`, and check that the data set survives writing it back out
+fn load(input: &str) -> (Header, DataSet) {
+    let (header, dset) = parse(input);
+
+    let mut writer = ArffWriter::new(Vec::new(), &header).unwrap();
+    writer.write_dataset(&dset).unwrap();
+    let output = String::from_utf8(writer.into_inner()).unwrap();
+    assert_eq!(DataSet::from_str(&output).unwrap(), dset);
+
+    (header, dset)
+}
+
+fn dtypes(header: &Header) -> Vec<&DType> {
+    header.attrs().iter().map(|attr| attr.dtype()).collect()
+}
+
+fn nominal(categories: &[&str]) -> DType {
+    DType::Nominal(categories.iter().map(|&c| c.to_owned()).collect())
+}
+
+#[test]
+fn weather_nominal() {
+    let (header, dset) = load(include_str!("weka/weather.nominal.arff"));
+
+    assert_eq!(header.name(), "weather.symbolic");
+    assert_eq!(
+        dtypes(&header),
+        vec![
+            &nominal(&["sunny", "overcast", "rainy"]),
+            &nominal(&["hot", "mild", "cool"]),
+            &nominal(&["high", "normal"]),
+            &nominal(&["TRUE", "FALSE"]),
+            &nominal(&["yes", "no"]),
+        ]
+    );
+    assert_eq!(dset.n_rows(), 14);
+    assert_eq!(dset.item(13, 0).as_str(), Ok("rainy"));
+}
+
+#[test]
+fn weather_numeric() {
+    let (header, dset) = load(include_str!("weka/weather.numeric.arff"));
+
+    assert_eq!(dtypes(&header)[1..3], [&DType::Real, &DType::Real]);
+    assert_eq!(dset.n_rows(), 14);
+    assert_eq!(dset.item(0, 1), Value::F64(85.0));
+}
+
+#[test]
+fn contact_lenses() {
+    let (header, dset) = load(include_str!("weka/contact-lenses.arff"));
+
+    // attribute names are separated from the categories by tabs
+    let names: Vec<_> = header.attrs().iter().map(|attr| attr.name()).collect();
+    assert_eq!(
+        names,
+        vec![
+            "age",
+            "spectacle-prescrip",
+            "astigmatism",
+            "tear-prod-rate",
+            "contact-lenses"
+        ]
+    );
+    assert_eq!(header.relation_comments().len(), 48);
+    assert_eq!(
+        header.relation_comments()[0],
+        " 1. Title: Database for fitting contact lenses"
+    );
+    assert!(header.comments().is_empty());
+    assert_eq!(dset.n_rows(), 24);
+    assert_eq!(dset.data_comments().len(), 3);
+}
+
+#[test]
+fn airline() {
+    let (header, dset) = load(include_str!("weka/airline.arff"));
+
+    match *header.attrs()[1].dtype() {
+        DType::Date(ref format) => assert_eq!(format.pattern(), "yyyy-MM-dd"),
+        ref dtype => panic!("expected date attribute, got {:?}", dtype),
+    }
+    assert_eq!(dset.n_rows(), 12);
+    assert_eq!(dset.item(0, 1), Value::Date(-662_688_000_000));
+}
+
+#[test]
+fn labor() {
+    let (header, dset) = load(include_str!("weka/labor.arff"));
+
+    assert_eq!(header.name(), "labor-neg-data");
+    assert_eq!(header.attrs().len(), 17);
+    assert_eq!(header.attrs()[4].name(), "cost-of-living-adjustment");
+    assert_eq!(*header.attrs()[4].dtype(), nominal(&["none", "tcf", "tc"]));
+    assert_eq!(dset.n_rows(), 5);
+    assert_eq!(dset.item(2, 0), Value::Missing);
+    assert_eq!(dset.item(1, 6).as_str(), Ok("ret_allw"));
+}
+
+#[test]
+fn timestamps() {
+    let (_, dset) = load(include_str!("weka/timestamps.arff"));
+
+    assert_eq!(dset.n_rows(), 2);
+    assert_eq!(dset.item(0, 0), Value::Date(986_299_932_000));
+}
+
+#[test]
+fn sparse() {
+    let (_, dset) = load(include_str!("weka/sparse.arff"));
+
+    assert_eq!(dset.n_rows(), 3);
+    assert_eq!(dset.item(1, 0).as_f64(), Ok(0.0));
+    assert_eq!(dset.item(1, 4).as_str(), Ok("class B"));
+    assert_eq!(dset.weights(), &[1.0, 1.0, 5.0]);
+}
+
+#[test]
+fn musk() {
+    // the writer does not support relational attributes
+    let (header, dset) = parse(include_str!("weka/musk.arff"));
+
+    match *header.attrs()[1].dtype() {
+        DType::Relational(ref attrs) => assert_eq!(attrs.len(), 3),
+        ref dtype => panic!("expected relational attribute, got {:?}", dtype),
+    }
+    assert_eq!(dset.n_rows(), 3);
+    match dset.item(1, 1) {
+        Value::Relational(bag) => assert_eq!(bag.n_rows(), 3),
+        value => panic!("expected relational value, got {:?}", value),
+    }
+}
+
+#[test]
+fn malformed_headers() {
+    let cases = [
+        (
+            "@RELATION x\n@ATTRIBUTE a int\n@DATA\n",
+            "line 2, column 14: column type 'int' not understood",
+        ),
+        (
+            "@RELATION x\n@ATTRIBUTE a NUMERICALLY\n@DATA\n",
+            "line 2, column 14: column type 'NUMERICALLY' not understood",
+        ),
+        (
+            "@RELATION x\n@ATTRIBUTE a STRINGY\n@DATA\n",
+            "line 2, column 14: column type 'STRINGY' not understood",
+        ),
+        (
+            "@RELATION x\n@ATTRIBUTE a\n@DATA\n",
+            "line 2, column 13: expected attribute type",
+        ),
+        (
+            "@RELATION x\n@ATTRIBUTE a NUMERIC",
+            "unexpected end of input",
+        ),
+        (
+            "@RELATION x\n@ATTRIBUTE a {b, c} d\n@DATA\n",
+            "line 2, column 21: expected end of line",
+        ),
+        (
+            "@RELATION x y\n@ATTRIBUTE a NUMERIC\n@DATA\n",
+            "line 1, column 13: expected end of line",
+        ),
+        (
+            "@RELATION x\n@ATTRIBUTE a DATE 'yyyy-MM-dd' x\n@DATA\n",
+            "line 2, column 32: expected end of line",
+        ),
+        (
+            "@RELATION x @DATA\n",
+            "line 1, column 13: expected end of line",
+        ),
+        (
+            "@RELATION x\n@ATTRIBUTE a NUMERIC @ATTRIBUTE b NUMERIC\n@DATA\n",
+            "line 2, column 22: expected end of line",
+        ),
+        (
+            "@RELATION x\n@ATTRIBUTE a {b, c}@DATA\n",
+            "line 2, column 20: expected end of line",
+        ),
+        (
+            "@RELATION x\nstray text\n@ATTRIBUTE a NUMERIC\n@DATA\n",
+            "line 2, column 1: expected `@RELATION`, `@ATTRIBUTE`, or `@DATA`",
+        ),
+        (
+            "@RELATION x\n@ATTRIBUTE bag RELATIONAL\n  @ATTRIBUTE a NUMERIC\n  a b\n@END bag\n@DATA\n",
+            "line 4, column 3: expected `@ATTRIBUTE` or `@END`",
+        ),
+    ];
+
+    for &(input, message) in &cases {
+        match arff::read_header(input) {
+            Err(e) => assert_eq!(e.to_string(), message, "input: {:?}", input),
+            Ok(header) => panic!("{:?} should not parse, got {:?}", input, header),
+        }
+    }
+}